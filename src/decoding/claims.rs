@@ -14,11 +14,11 @@ use serde::Deserialize;
 pub fn decode<T: for<'a> Deserialize<'a> + Clone + ToOwned>(claims: &str) -> Result<T, ClaimsDecodeError> {
     
     // Decode the base64 string
-    let claims_json: Vec<u8> = base64::engine::general_purpose::STANDARD.decode(claims).map_err(|e| ClaimsDecodeError::Base64Error(e))?;
+    let claims_json: Vec<u8> = super::DECODING_ENGINE.decode(claims).map_err(ClaimsDecodeError::Base64Error)?;
 
     // Deserialize the JSON bytes
     let bytes: &[u8]= claims_json.as_slice();
-    let result: T = serde_json::from_slice(bytes).map_err(|e| ClaimsDecodeError::JsonError(e))?;
+    let result: T = serde_json::from_slice(bytes).map_err(ClaimsDecodeError::JsonError)?;
 
     let result = result.clone();
 
@@ -1,9 +1,10 @@
 use std::str::FromStr;
 
 use base64::Engine;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 
-use crate::model::header::{Algorithm, Header};
+use crate::model::header::{Algorithm, Header, REGISTERED_PARAMETERS};
 
 /// Decodes a base64 string into a header
 /// 
@@ -14,23 +15,46 @@ use crate::model::header::{Algorithm, Header};
 /// A `Result` containing either the decoded header or an error
 pub fn decode(base64_str: &str) -> Result<Header, HeaderDecodeError> {
     // Decode a base64 string into a vector of bytes
-    let decoded_bytes = base64::engine::general_purpose::STANDARD.decode(base64_str).map_err(|e| HeaderDecodeError::Base64Error(e))?;
+    let decoded_bytes = super::DECODING_ENGINE.decode(base64_str).map_err(HeaderDecodeError::Base64Error)?;
 
     // Convert the vector of bytes to a String
     let decoded_string = String::from_utf8(decoded_bytes).map_err(|e| HeaderDecodeError::Other(e.to_string()))?;
 
     // Convert the String into a JSON object
-    let full_header: Value = serde_json::from_str(&decoded_string).map_err(|e| HeaderDecodeError::JsonError(e))?;
+    let full_header: Value = serde_json::from_str(&decoded_string).map_err(HeaderDecodeError::JsonError)?;
 
     // Take the algorithm and construct the header object
     let alg = full_header["alg"].as_str().ok_or_else(|| HeaderDecodeError::MissingFieldError("Missing 'alg' field".to_string()))?;
     let alg = Algorithm::from_str(alg).map_err(|_| HeaderDecodeError::UnsupportedAlgorithm(alg.to_string()))?;
     
-    // Create a new header object with the algorithm
-    let header = Header::new(alg);
+    // Create a new header object with the algorithm and the optional parameters
+    let mut header = Header::new(alg);
+    header.typ = optional_field(&full_header, "typ")?;
+    header.cty = optional_field(&full_header, "cty")?;
+    header.kid = optional_field(&full_header, "kid")?;
+    header.jku = optional_field(&full_header, "jku")?;
+    header.jwk = optional_field(&full_header, "jwk")?;
+    header.x5u = optional_field(&full_header, "x5u")?;
+    header.x5c = optional_field(&full_header, "x5c")?;
+    header.x5t = optional_field(&full_header, "x5t")?;
+    header.x5t_s256 = optional_field(&full_header, "x5t#S256")?;
+    header.crit = optional_field(&full_header, "crit")?;
+
+    // Keep every other parameter as it is
+    if let Value::Object(members) = full_header {
+        header.extra = members.into_iter().filter(|(name, _)| !REGISTERED_PARAMETERS.contains(&name.as_str())).collect();
+    }
     Ok(header)
 }
 
+/// Reads an optional header parameter, returning an error if it is present with the wrong type
+fn optional_field<T: DeserializeOwned>(full_header: &Value, name: &str) -> Result<Option<T>, HeaderDecodeError> {
+    match full_header.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value.clone()).map(Some).map_err(HeaderDecodeError::JsonError),
+    }
+}
+
 #[derive(Debug)]
 /// Error type returned when decoding a header fails
 /// 
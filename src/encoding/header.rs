@@ -1,7 +1,6 @@
 use base64::Engine;
-use serde_json::json;
 
-use crate::model::header::Header;
+use crate::model::header::{Header, REGISTERED_PARAMETERS};
 
 use super::ENCODING_ENGINE;
 
@@ -14,12 +13,14 @@ use super::ENCODING_ENGINE;
 /// A base64 encoded string representation of the header
 pub fn encode(header: &Header) -> Result<String, HeaderEncodeError> {
 
-    // Convert the header to a JSON string
-    let header = json!({
-        "typ": "JWT",
-        "alg": header.alg,
-    });
-    let header_json = serde_json::to_string(&header).map_err(|e| HeaderEncodeError::JsonError(e))?;
+    // An extra parameter with a registered name would replace the field, e.g. change the algorithm
+    if let Some(name) = header.extra.keys().find(|name| REGISTERED_PARAMETERS.contains(&name.as_str())) {
+        return Err(HeaderEncodeError::RegisteredParameter(name.clone()));
+    }
+
+    // Convert the header to JSON, the parameters that are not set are left out
+    let header_value = serde_json::to_value(header).map_err(HeaderEncodeError::JsonError)?;
+    let header_json = serde_json::to_string(&header_value).map_err(HeaderEncodeError::JsonError)?;
 
     // Encode the JSON string to base64 
     let header_base64: String = ENCODING_ENGINE.encode(header_json);
@@ -31,10 +32,11 @@ pub fn encode(header: &Header) -> Result<String, HeaderEncodeError> {
 /// 
 /// # Variants
 /// * `JsonError`: JSON serialization error
-/// * `Base64Error`: Base64 encoding error
-/// * `Other`: Other errors
+/// * `RegisteredParameter`: An extra parameter has the name of a registered parameter
 pub enum HeaderEncodeError {
     /// JSON serialization error
     #[allow(dead_code)]
     JsonError(serde_json::Error),
+    /// An extra parameter has the name of a registered parameter, e.g. `alg`, which must be set with its field
+    RegisteredParameter(String),
 }
\ No newline at end of file
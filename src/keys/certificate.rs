@@ -0,0 +1,337 @@
+use std::fmt::Display;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use pkcs8::der::{Decode, Encode};
+use pkcs8::{AssociatedOid, ObjectIdentifier};
+use rsa::Pkcs1v15Sign;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use x509_cert::ext::pkix::{BasicConstraints, KeyUsage};
+use x509_cert::Certificate;
+
+use crate::encoding::ENCODING_ENGINE;
+use crate::model::header::Header;
+
+use super::decoding_key::{read_spki, DecodingKey, DecodingKeyKind};
+use super::policy::KeyPolicy;
+use super::{KeyError, ED25519_OID, ED448_OID};
+
+/// Reads an X.509 certificate from DER and returns its public key as SPKI DER
+///
+/// The certificate must be valid at the current time, and its key usage must allow digital signatures.
+/// The signature of the certificate itself is not checked, the caller must already trust it.
+pub(crate) fn read_certificate_key(der: &[u8]) -> Result<Vec<u8>, KeyError> {
+    let certificate = Certificate::from_der(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+    check_certificate(&certificate, SystemTime::now())?;
+
+    certificate.tbs_certificate.subject_public_key_info.to_der().map_err(|err| KeyError::InvalidKey(err.to_string()))
+}
+
+/// Checks a certificate is valid at `now` and can be used to verify signatures
+pub(crate) fn check_certificate(certificate: &Certificate, now: SystemTime) -> Result<(), KeyError> {
+    let now = now.duration_since(UNIX_EPOCH).map_err(|err| KeyError::InvalidCertificate(err.to_string()))?;
+    let validity = &certificate.tbs_certificate.validity;
+
+    if now < validity.not_before.to_unix_duration() {
+        return Err(KeyError::InvalidCertificate(format!("Certificate is not valid until {}", validity.not_before)));
+    }
+    if now > validity.not_after.to_unix_duration() {
+        return Err(KeyError::InvalidCertificate(format!("Certificate expired at {}", validity.not_after)));
+    }
+
+    // Without a key usage extension the key can be used for anything
+    let extensions = certificate.tbs_certificate.extensions.as_deref().unwrap_or_default();
+    if let Some(extension) = extensions.iter().find(|extension| extension.extn_id == KeyUsage::OID) {
+        let key_usage = KeyUsage::from_der(extension.extn_value.as_bytes()).map_err(|err| KeyError::InvalidCertificate(err.to_string()))?;
+        if !key_usage.digital_signature() {
+            return Err(KeyError::InvalidCertificate("Certificate key usage does not allow digital signatures".to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// The object identifiers of the certificate signature algorithms, from RFC 4055, RFC 5758 and RFC 8410
+const SHA256_WITH_RSA_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.11");
+const SHA384_WITH_RSA_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.12");
+const SHA512_WITH_RSA_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.13");
+const ECDSA_WITH_SHA256_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const ECDSA_WITH_SHA384_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.3");
+const ECDSA_WITH_SHA512_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.4");
+
+/// The certificates trusted to issue the certificate chains in `x5c` headers
+///
+/// A chain is trusted if its last certificate is one of the trust anchors, or is signed by one that is itself a valid CA.
+///
+/// # Example
+/// ```rust,no_run
+/// use super_simple_jwt::{verify_with_x5c, TrustAnchors};
+/// # #[derive(serde::Serialize, serde::Deserialize, Clone)]
+/// # struct Claims { exp: usize, sub: String }
+///
+/// let pem = std::fs::read_to_string("root_ca.pem").unwrap();
+/// let trust_anchors = TrustAnchors::from_pem(&pem).unwrap();
+///
+/// let claims: Claims = verify_with_x5c("pretend_this.is_a.valid_signed_token", &trust_anchors).unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct TrustAnchors {
+    certificates: Vec<Certificate>,
+}
+
+impl TrustAnchors {
+
+    /// Creates an empty set of trust anchors, which trusts no chains
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads trust anchors from one or more PEM certificates (`CERTIFICATE`)
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded certificates, one after another
+    ///
+    /// # Returns
+    /// * `Result<TrustAnchors, KeyError>` - The trust anchors, or an error if a certificate could not be read
+    pub fn from_pem(pem: &str) -> Result<Self, KeyError> {
+        let mut trust_anchors = Self::new();
+        trust_anchors.add_pem(pem)?;
+        Ok(trust_anchors)
+    }
+
+    /// Adds one or more PEM certificates (`CERTIFICATE`) as trust anchors
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded certificates, one after another
+    pub fn add_pem(&mut self, pem: &str) -> Result<(), KeyError> {
+        let certificates = Certificate::load_pem_chain(pem.as_bytes()).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+        self.certificates.extend(certificates);
+        Ok(())
+    }
+
+    /// Adds a DER certificate as a trust anchor
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded certificate
+    pub fn add_der(&mut self, der: &[u8]) -> Result<(), KeyError> {
+        let certificate = Certificate::from_der(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+        self.certificates.push(certificate);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Error type returned when the `x5c` certificate chain of a token cannot be trusted
+///
+/// Certificates are numbered by their position in `x5c`, the leaf certificate is 0.
+/// A trust anchor that signs the last certificate is numbered as if it came after it, i.e. the length of `x5c`.
+///
+/// # Variants
+/// * `MissingChain` - The header has no `x5c` parameter, or it is empty
+/// * `InvalidCertificate` - A certificate could not be read
+/// * `NotYetValid` - A certificate is not valid yet
+/// * `Expired` - A certificate has expired
+/// * `KeyUsage` - The key usage of a certificate does not allow it to sign tokens, or to sign certificates for a CA
+/// * `NotCa` - A certificate used to sign another certificate is not a CA
+/// * `PathLengthExceeded` - A CA has more CAs below it than its path length constraint allows
+/// * `IssuerMismatch` - The issuer of a certificate is not the subject of the next certificate
+/// * `InvalidSignature` - The signature of a certificate does not verify with the key of its issuer
+/// * `UntrustedRoot` - The chain does not end at, or is not signed by, a trust anchor
+/// * `ThumbprintMismatch` - The `x5t` or `x5t#S256` thumbprint does not match the leaf certificate
+pub enum CertificateChainError {
+    /// The header has no `x5c` parameter, or it is empty
+    MissingChain,
+    /// A certificate could not be read
+    InvalidCertificate(usize, String),
+    /// A certificate is not valid yet
+    NotYetValid(usize),
+    /// A certificate has expired
+    Expired(usize),
+    /// The key usage of a certificate does not allow it to sign tokens, or to sign certificates for a CA
+    KeyUsage(usize),
+    /// A certificate used to sign another certificate is not a CA
+    NotCa(usize),
+    /// A CA has more CAs below it than its path length constraint allows
+    PathLengthExceeded(usize),
+    /// The issuer of a certificate is not the subject of the next certificate
+    IssuerMismatch(usize),
+    /// The signature of a certificate does not verify with the key of its issuer
+    InvalidSignature(usize),
+    /// The chain does not end at, or is not signed by, a trust anchor
+    UntrustedRoot,
+    /// The named thumbprint does not match the leaf certificate
+    ThumbprintMismatch(String),
+}
+
+impl Display for CertificateChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CertificateChainError::MissingChain => write!(f, "The token has no x5c certificate chain"),
+            CertificateChainError::InvalidCertificate(index, message) => write!(f, "Certificate {} could not be read: {}", index, message),
+            CertificateChainError::NotYetValid(index) => write!(f, "Certificate {} is not valid yet", index),
+            CertificateChainError::Expired(index) => write!(f, "Certificate {} has expired", index),
+            CertificateChainError::KeyUsage(index) => write!(f, "The key usage of certificate {} does not allow this use", index),
+            CertificateChainError::NotCa(index) => write!(f, "Certificate {} is not a CA", index),
+            CertificateChainError::PathLengthExceeded(index) => write!(f, "The path length constraint of certificate {} is exceeded", index),
+            CertificateChainError::IssuerMismatch(index) => write!(f, "The issuer of certificate {} is not the next certificate", index),
+            CertificateChainError::InvalidSignature(index) => write!(f, "The signature of certificate {} is invalid", index),
+            CertificateChainError::UntrustedRoot => write!(f, "The certificate chain is not issued by a trust anchor"),
+            CertificateChainError::ThumbprintMismatch(name) => write!(f, "The {} thumbprint does not match the leaf certificate", name),
+        }
+    }
+}
+
+/// Validates the `x5c` certificate chain of a header against the trust anchors at `now`, returning the key of the leaf certificate
+///
+/// Revocation is not checked, as that cannot be done offline.
+pub(crate) fn validate_chain(header: &Header, trust_anchors: &TrustAnchors, now: SystemTime) -> Result<DecodingKey, CertificateChainError> {
+    let x5c = header.x5c.as_deref().unwrap_or_default();
+    if x5c.is_empty() {
+        return Err(CertificateChainError::MissingChain);
+    }
+
+    // The chain is standard base64 DER, not base64url
+    let mut ders = Vec::with_capacity(x5c.len());
+    let mut chain = Vec::with_capacity(x5c.len());
+    for (index, certificate) in x5c.iter().enumerate() {
+        let der = STANDARD.decode(certificate).map_err(|err| CertificateChainError::InvalidCertificate(index, err.to_string()))?;
+        chain.push(Certificate::from_der(&der).map_err(|err| CertificateChainError::InvalidCertificate(index, err.to_string()))?);
+        ders.push(der);
+    }
+
+    // Check the thumbprints of the leaf certificate
+    if header.x5t.as_ref().is_some_and(|x5t| *x5t != ENCODING_ENGINE.encode(Sha1::digest(&ders[0]))) {
+        return Err(CertificateChainError::ThumbprintMismatch("x5t".to_string()));
+    }
+    if header.x5t_s256.as_ref().is_some_and(|x5t_s256| *x5t_s256 != ENCODING_ENGINE.encode(Sha256::digest(&ders[0]))) {
+        return Err(CertificateChainError::ThumbprintMismatch("x5t#S256".to_string()));
+    }
+
+    let now = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    for (index, certificate) in chain.iter().enumerate() {
+        check_validity(certificate, index, now)?;
+
+        // The leaf must be allowed to sign tokens, every other certificate must be a CA that can sign the one before it
+        let key_usage = extension::<KeyUsage>(certificate).map_err(|message| CertificateChainError::InvalidCertificate(index, message))?;
+        if index == 0 {
+            if key_usage.is_some_and(|key_usage| !key_usage.digital_signature()) {
+                return Err(CertificateChainError::KeyUsage(index));
+            }
+            continue;
+        }
+        check_ca(certificate, index, key_usage)?;
+
+        let issued = &chain[index - 1];
+        if issued.tbs_certificate.issuer != certificate.tbs_certificate.subject {
+            return Err(CertificateChainError::IssuerMismatch(index - 1));
+        }
+        if !verify_signature(issued, certificate).map_err(|message| CertificateChainError::InvalidCertificate(index - 1, message))? {
+            return Err(CertificateChainError::InvalidSignature(index - 1));
+        }
+    }
+
+    // The chain is trusted if it ends at a trust anchor, or is signed by one
+    let last = chain.len() - 1;
+    let ends_at_anchor = trust_anchors.certificates.iter().any(|anchor| anchor.to_der().is_ok_and(|der| der == ders[last]));
+    if !ends_at_anchor {
+        // An anchor that signs the chain is checked as if it were the next certificate in it, and any one that passes is enough
+        let mut result = Err(CertificateChainError::UntrustedRoot);
+        for anchor in &trust_anchors.certificates {
+            if anchor.tbs_certificate.subject != chain[last].tbs_certificate.issuer || !verify_signature(&chain[last], anchor).unwrap_or(false) {
+                continue;
+            }
+            result = check_anchor(anchor, chain.len(), now);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result?;
+    }
+
+    let spki = chain[0].tbs_certificate.subject_public_key_info.to_der().map_err(|err| CertificateChainError::InvalidCertificate(0, err.to_string()))?;
+    read_spki(&spki).and_then(|kind| DecodingKey::checked(kind, &KeyPolicy::default())).map_err(|err| CertificateChainError::InvalidCertificate(0, err.to_string()))
+}
+
+/// Checks the certificate at `index` in the chain is within its validity window at `now`, the time since the Unix epoch
+fn check_validity(certificate: &Certificate, index: usize, now: Duration) -> Result<(), CertificateChainError> {
+    let validity = &certificate.tbs_certificate.validity;
+    if now < validity.not_before.to_unix_duration() {
+        return Err(CertificateChainError::NotYetValid(index));
+    }
+    if now > validity.not_after.to_unix_duration() {
+        return Err(CertificateChainError::Expired(index));
+    }
+    Ok(())
+}
+
+/// Checks a trust anchor that signed the last certificate of the chain is valid and a CA, numbering it `index`, the length of the chain
+fn check_anchor(anchor: &Certificate, index: usize, now: Duration) -> Result<(), CertificateChainError> {
+    check_validity(anchor, index, now)?;
+    let key_usage = extension::<KeyUsage>(anchor).map_err(|message| CertificateChainError::InvalidCertificate(index, message))?;
+    check_ca(anchor, index, key_usage)
+}
+
+/// Checks the certificate at `index` in the chain is a CA that can sign certificates, with at least `index - 1` CAs below it
+fn check_ca(certificate: &Certificate, index: usize, key_usage: Option<KeyUsage>) -> Result<(), CertificateChainError> {
+    let basic_constraints = extension::<BasicConstraints>(certificate).map_err(|message| CertificateChainError::InvalidCertificate(index, message))?;
+    let Some(basic_constraints) = basic_constraints.filter(|basic_constraints| basic_constraints.ca) else {
+        return Err(CertificateChainError::NotCa(index));
+    };
+    if key_usage.is_some_and(|key_usage| !key_usage.key_cert_sign()) {
+        return Err(CertificateChainError::KeyUsage(index));
+    }
+
+    // The path length counts the CAs between this certificate and the leaf
+    if basic_constraints.path_len_constraint.is_some_and(|path_length| usize::from(path_length) < index - 1) {
+        return Err(CertificateChainError::PathLengthExceeded(index));
+    }
+    Ok(())
+}
+
+/// Reads an extension from a certificate, if it is present
+fn extension<E: AssociatedOid + for<'a> Decode<'a>>(certificate: &Certificate) -> Result<Option<E>, String> {
+    let extensions = certificate.tbs_certificate.extensions.as_deref().unwrap_or_default();
+    match extensions.iter().find(|extension| extension.extn_id == E::OID) {
+        Some(extension) => E::from_der(extension.extn_value.as_bytes()).map(Some).map_err(|err| err.to_string()),
+        None => Ok(None),
+    }
+}
+
+/// Verifies the signature of a certificate with the public key of its issuer
+fn verify_signature(certificate: &Certificate, issuer: &Certificate) -> Result<bool, String> {
+    let spki = issuer.tbs_certificate.subject_public_key_info.to_der().map_err(|err| err.to_string())?;
+    let issuer_key = read_spki(&spki).map_err(|err| err.to_string())?;
+    let tbs = certificate.tbs_certificate.to_der().map_err(|err| err.to_string())?;
+    let signature = certificate.signature.as_bytes().ok_or_else(|| "Signature is not a whole number of bytes".to_string())?;
+
+    // Match the signature algorithm to pick the padding scheme and hash function, the key type must match it
+    let verified = match (certificate.signature_algorithm.oid, &issuer_key) {
+        (SHA256_WITH_RSA_OID, DecodingKeyKind::Rsa(public_key)) => public_key.verify(Pkcs1v15Sign::new::<Sha256>(), &Sha256::digest(&tbs), signature).is_ok(),
+        (SHA384_WITH_RSA_OID, DecodingKeyKind::Rsa(public_key)) => public_key.verify(Pkcs1v15Sign::new::<Sha384>(), &Sha384::digest(&tbs), signature).is_ok(),
+        (SHA512_WITH_RSA_OID, DecodingKeyKind::Rsa(public_key)) => public_key.verify(Pkcs1v15Sign::new::<Sha512>(), &Sha512::digest(&tbs), signature).is_ok(),
+        (ECDSA_WITH_SHA256_OID, _) => verify_ecdsa(&issuer_key, &Sha256::digest(&tbs), signature),
+        (ECDSA_WITH_SHA384_OID, _) => verify_ecdsa(&issuer_key, &Sha384::digest(&tbs), signature),
+        (ECDSA_WITH_SHA512_OID, _) => verify_ecdsa(&issuer_key, &Sha512::digest(&tbs), signature),
+        (ED25519_OID, DecodingKeyKind::Ed25519(public_key)) => ed25519_dalek::Signature::from_slice(signature).is_ok_and(|signature| public_key.verify_strict(&tbs, &signature).is_ok()),
+        (ED448_OID, DecodingKeyKind::Ed448(public_key)) => ed448_goldilocks_plus::Signature::try_from(signature).is_ok_and(|signature| public_key.verify_raw(&signature, &tbs).is_ok()),
+        (oid, _) => return Err(format!("Unsupported signature algorithm {} for the key of the issuer", oid)),
+    };
+    Ok(verified)
+}
+
+/// Verifies a DER encoded ECDSA signature of a digest, with the curve of the issuer key
+fn verify_ecdsa(issuer_key: &DecodingKeyKind, digest: &[u8], signature: &[u8]) -> bool {
+    match issuer_key {
+        DecodingKeyKind::P256(public_key) => p256::ecdsa::Signature::from_der(signature).is_ok_and(|signature| public_key.verify_prehash(digest, &signature).is_ok()),
+        DecodingKeyKind::P384(public_key) => p384::ecdsa::Signature::from_der(signature).is_ok_and(|signature| public_key.verify_prehash(digest, &signature).is_ok()),
+        DecodingKeyKind::P521(public_key) => p521::ecdsa::Signature::from_der(signature).is_ok_and(|signature| public_key.verify_prehash(digest, &signature).is_ok()),
+        DecodingKeyKind::Secp256k1(public_key) => k256::ecdsa::Signature::from_der(signature).is_ok_and(|signature| {
+            let signature = signature.normalize_s().unwrap_or(signature);
+            public_key.verify_prehash(digest, &signature).is_ok()
+        }),
+        _ => false,
+    }
+}
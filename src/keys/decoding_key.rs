@@ -0,0 +1,530 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use pkcs8::spki::SubjectPublicKeyInfoRef;
+use pkcs8::der::pem::LineEnding;
+use pkcs8::{DecodePublicKey, EncodePublicKey};
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::RsaPublicKey;
+use zeroize::Zeroizing;
+
+use crate::model::header::Algorithm;
+use crate::model::jwk::{Jwk, JwkKey};
+
+use super::certificate::read_certificate_key;
+use super::jwk;
+use super::policy::KeyPolicy;
+use super::{is_key_document, key_type_name, wrong_format, EcCurve, KeyError, KeyFormat, EC_OID, ED25519_OID, ED448_OID, RSA_OID};
+
+/// A public key or shared secret used to verify tokens
+///
+/// The key is read once and can then be used to verify any number of tokens.
+/// Cloning is cheap as the key is shared, and it can be sent between threads.
+/// A shared secret is held in `Zeroizing`, so it is wiped from memory when the last clone is dropped, and `Debug` only shows the type of key.
+/// Public keys hold nothing secret and are not wiped.
+///
+/// # Example
+/// ```rust,no_run
+/// use super_simple_jwt::{verify_with_key, DecodingKey};
+/// # #[derive(serde::Serialize, serde::Deserialize, Clone)]
+/// # struct Claims { exp: usize, sub: String }
+///
+/// let pem = std::fs::read_to_string("public.pem").unwrap();
+/// let key = DecodingKey::from_rsa_pem(&pem).unwrap();
+///
+/// let claims: Claims = verify_with_key("pretend_this.is_a.valid_signed_token", &key).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct DecodingKey {
+    pub(crate) kind: Arc<DecodingKeyKind>,
+    /// Whether an HMAC secret must be as long as the hash output, from the policy it was read with
+    pub(crate) hmac_hash_length: bool,
+}
+
+/// The parsed key, which differs for each key type
+pub(crate) enum DecodingKeyKind {
+    Rsa(RsaPublicKey),
+    P256(p256::ecdsa::VerifyingKey),
+    P384(p384::ecdsa::VerifyingKey),
+    P521(p521::ecdsa::VerifyingKey),
+    Secp256k1(k256::ecdsa::VerifyingKey),
+    Ed25519(ed25519_dalek::VerifyingKey),
+    Ed448(ed448_goldilocks_plus::VerifyingKey),
+    Hmac(Zeroizing<Vec<u8>>),
+}
+
+impl DecodingKey {
+
+    /// Reads an RSA public key in SPKI (`PUBLIC KEY`) or PKCS#1 (`RSA PUBLIC KEY`) PEM format, or from an X.509 certificate (`CERTIFICATE`)
+    ///
+    /// The format is detected from the PEM block.
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded public key
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read or is weaker than `KeyPolicy::default()`
+    pub fn from_rsa_pem(pem: &str) -> Result<Self, KeyError> {
+        Self::from_rsa_pem_with_policy(pem, &KeyPolicy::default())
+    }
+
+    /// Reads an RSA public key in SPKI or PKCS#1 PEM format, or from an X.509 certificate, accepting keys that meet a policy other than the default
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded public key
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()`
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read or is weaker than the policy
+    pub fn from_rsa_pem_with_policy(pem: &str, policy: &KeyPolicy) -> Result<Self, KeyError> {
+        let (format, document) = KeyFormat::read(pem)?;
+        Self::checked(read_rsa(format, document.as_bytes())?, policy)
+    }
+
+    /// Reads an RSA public key in SPKI or PKCS#1 DER format, or from a DER X.509 certificate
+    ///
+    /// The format is detected from the structure of the DER.
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded public key
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read or is weaker than `KeyPolicy::default()`
+    pub fn from_rsa_der(der: &[u8]) -> Result<Self, KeyError> {
+        Self::from_rsa_der_with_policy(der, &KeyPolicy::default())
+    }
+
+    /// Reads an RSA public key in SPKI or PKCS#1 DER format, or from a DER X.509 certificate, accepting keys that meet a policy other than the default
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded public key
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()`
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read or is weaker than the policy
+    pub fn from_rsa_der_with_policy(der: &[u8], policy: &KeyPolicy) -> Result<Self, KeyError> {
+        Self::checked(read_rsa(KeyFormat::detect(der)?, der)?, policy)
+    }
+
+    /// Reads an EC public key in SPKI (`PUBLIC KEY`) PEM format, or from an X.509 certificate (`CERTIFICATE`)
+    ///
+    /// The curve is read from the key, which must be one of P-256, P-384, P-521 or secp256k1.
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded public key
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read
+    pub fn from_ec_pem(pem: &str) -> Result<Self, KeyError> {
+        let (format, document) = KeyFormat::read(pem)?;
+        read_ec(format, document.as_bytes()).map(Self::new)
+    }
+
+    /// Reads an EC public key in SPKI DER format, or from a DER X.509 certificate
+    ///
+    /// The curve is read from the key, which must be one of P-256, P-384, P-521 or secp256k1.
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded public key
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read
+    pub fn from_ec_der(der: &[u8]) -> Result<Self, KeyError> {
+        read_ec(KeyFormat::detect(der)?, der).map(Self::new)
+    }
+
+    /// Reads an Ed25519 or Ed448 public key in SPKI (`PUBLIC KEY`) PEM format, or from an X.509 certificate (`CERTIFICATE`)
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded public key
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read
+    pub fn from_ed_pem(pem: &str) -> Result<Self, KeyError> {
+        let (format, document) = KeyFormat::read(pem)?;
+        read_ed(format, document.as_bytes()).map(Self::new)
+    }
+
+    /// Reads an Ed25519 or Ed448 public key in SPKI DER format, or from a DER X.509 certificate
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded public key
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read
+    pub fn from_ed_der(der: &[u8]) -> Result<Self, KeyError> {
+        read_ed(KeyFormat::detect(der)?, der).map(Self::new)
+    }
+
+    /// Reads the public key of any supported type from an X.509 certificate in PEM (`CERTIFICATE`) format
+    ///
+    /// Certificates that are not valid at the current time, or whose key usage forbids digital signatures, are rejected.
+    /// The signature of the certificate is not checked, so it must come from a trusted source.
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded certificate
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read, the certificate cannot be used or the key is weaker than `KeyPolicy::default()`
+    pub fn from_certificate_pem(pem: &str) -> Result<Self, KeyError> {
+        Self::from_certificate_pem_with_policy(pem, &KeyPolicy::default())
+    }
+
+    /// Reads the public key of any supported type from a PEM X.509 certificate, accepting keys that meet a policy other than the default
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded certificate
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()`
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read, the certificate cannot be used or the key is weaker than the policy
+    pub fn from_certificate_pem_with_policy(pem: &str, policy: &KeyPolicy) -> Result<Self, KeyError> {
+        let (format, document) = KeyFormat::read(pem)?;
+        Self::checked(read_certificate(format, document.as_bytes())?, policy)
+    }
+
+    /// Reads the public key of any supported type from a DER X.509 certificate
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded certificate
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read, the certificate cannot be used or the key is weaker than `KeyPolicy::default()`
+    pub fn from_certificate_der(der: &[u8]) -> Result<Self, KeyError> {
+        Self::from_certificate_der_with_policy(der, &KeyPolicy::default())
+    }
+
+    /// Reads the public key of any supported type from a DER X.509 certificate, accepting keys that meet a policy other than the default
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded certificate
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()`
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read, the certificate cannot be used or the key is weaker than the policy
+    pub fn from_certificate_der_with_policy(der: &[u8], policy: &KeyPolicy) -> Result<Self, KeyError> {
+        Self::checked(read_certificate(KeyFormat::detect(der)?, der)?, policy)
+    }
+
+    /// Reads an Ed25519 or Ed448 public key from an OKP JWK, as defined in RFC 8037
+    ///
+    /// # Arguments
+    /// * `jwk` - The JWK as a JSON string
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read
+    pub fn from_okp_jwk(jwk: &str) -> Result<Self, KeyError> {
+        let jwk: Jwk = serde_json::from_str(jwk).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+        if !matches!(jwk.key, JwkKey::Okp { .. }) {
+            return Err(KeyError::InvalidKey("JWK must have a 'kty' of 'OKP'".to_string()));
+        }
+        Self::from_jwk(&jwk)
+    }
+
+    /// Reads a public key or shared secret from a JWK of any supported key type
+    ///
+    /// The private key of a private JWK is ignored, `oct` JWKs are read as a shared secret for the HMAC algorithms.
+    /// If the JWK has a `use` or `key_ops` member, it must allow verifying.
+    ///
+    /// # Arguments
+    /// * `jwk` - The JWK
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read or is weaker than `KeyPolicy::default()`
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self, KeyError> {
+        Self::from_jwk_with_policy(jwk, &KeyPolicy::default())
+    }
+
+    /// Reads a public key or shared secret from a JWK, accepting keys that meet a policy other than the default
+    ///
+    /// # Arguments
+    /// * `jwk` - The JWK
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()`
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or an error if it could not be read or is weaker than the policy
+    pub fn from_jwk_with_policy(jwk: &Jwk, policy: &KeyPolicy) -> Result<Self, KeyError> {
+        if !jwk.allows("verify") {
+            return Err(KeyError::IncompatibleKey("JWK 'use' or 'key_ops' does not allow verifying".to_string()));
+        }
+        // The secret of an `oct` JWK that names its algorithm must be long enough for it
+        let algorithm = jwk.alg.as_deref().and_then(|alg| alg.parse::<Algorithm>().ok());
+        Self::checked_for(jwk::read_public(&jwk.key)?, policy, algorithm.as_ref())
+    }
+
+    /// Exports the key as a public JWK, or an `oct` JWK for a shared secret
+    ///
+    /// Only the key material is set, `kid` and the other members can be set on the returned JWK.
+    ///
+    /// # Returns
+    /// * `Jwk` - The JWK
+    pub fn to_jwk(&self) -> Jwk {
+        Jwk::new(jwk::write_public(&self.kind))
+    }
+
+    /// Exports the public key in SPKI (`PUBLIC KEY`) PEM format
+    ///
+    /// # Returns
+    /// * `Result<String, KeyError>` - The PEM encoded key, or `IncompatibleKey` for a shared secret
+    pub fn to_spki_pem(&self) -> Result<String, KeyError> {
+        let der = self.to_spki_der()?;
+        pkcs8::der::pem::encode_string("PUBLIC KEY", LineEnding::LF, &der).map_err(|err| KeyError::InvalidKey(err.to_string()))
+    }
+
+    /// Exports the public key in SPKI DER format
+    ///
+    /// # Returns
+    /// * `Result<Vec<u8>, KeyError>` - The DER encoded key, or `IncompatibleKey` for a shared secret
+    pub fn to_spki_der(&self) -> Result<Vec<u8>, KeyError> {
+        let document = match self.kind.as_ref() {
+            DecodingKeyKind::Rsa(public_key) => public_key.to_public_key_der(),
+            DecodingKeyKind::P256(verifying_key) => verifying_key.to_public_key_der(),
+            DecodingKeyKind::P384(verifying_key) => verifying_key.to_public_key_der(),
+            DecodingKeyKind::P521(verifying_key) => p521::PublicKey::from_affine(*verifying_key.as_affine()).map_err(|err| KeyError::InvalidKey(err.to_string()))?.to_public_key_der(),
+            DecodingKeyKind::Secp256k1(verifying_key) => verifying_key.to_public_key_der(),
+            DecodingKeyKind::Ed25519(verifying_key) => verifying_key.to_public_key_der(),
+            DecodingKeyKind::Ed448(verifying_key) => verifying_key.to_public_key_der(),
+            DecodingKeyKind::Hmac(_) => return Err(KeyError::IncompatibleKey("Shared secrets have no public key".to_string())),
+        };
+        document.map(|document| document.into_vec()).map_err(|err| KeyError::InvalidKey(err.to_string()))
+    }
+
+    /// Computes the RFC 7638 SHA-256 thumbprint of the key
+    ///
+    /// # Returns
+    /// * `String` - The base64url encoded thumbprint
+    pub fn thumbprint(&self) -> String {
+        self.to_jwk().thumbprint()
+    }
+
+    /// The RFC 7638 SHA-256 thumbprint of the key as an RFC 9278 URI
+    ///
+    /// # Returns
+    /// * `String` - The URI, `urn:ietf:params:oauth:jwk-thumbprint:sha-256:` followed by the thumbprint
+    pub fn thumbprint_uri(&self) -> String {
+        self.to_jwk().thumbprint_uri()
+    }
+
+    /// Creates a key from a shared secret, for use with the HMAC algorithms
+    ///
+    /// The secret must be at least 32 bytes, which is checked here. It must also be at least as long as the output of the hash
+    /// function of the algorithm, e.g. 48 bytes for `HS384`, which is checked when verifying.
+    ///
+    /// # Arguments
+    /// * `secret` - The shared secret
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or `WeakKey` if the secret is shorter than `KeyPolicy::default()` allows
+    pub fn from_secret(secret: &[u8]) -> Result<Self, KeyError> {
+        Self::from_secret_with_policy(secret, &KeyPolicy::default())
+    }
+
+    /// Creates a key from a shared secret, checking its length against a policy other than the default
+    ///
+    /// # Arguments
+    /// * `secret` - The shared secret
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()` to accept shorter secrets
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or `WeakKey` if the secret is shorter than the policy allows
+    pub fn from_secret_with_policy(secret: &[u8], policy: &KeyPolicy) -> Result<Self, KeyError> {
+        Self::from_secret_vec_with_policy(secret.to_vec(), policy)
+    }
+
+    /// Creates a key from a shared secret, taking ownership of it so no copy is left behind
+    ///
+    /// The buffer is moved into the key without copying, and wiped when the key is dropped.
+    ///
+    /// # Arguments
+    /// * `secret` - The shared secret, as a `Vec<u8>` or `Zeroizing<Vec<u8>>`
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or `WeakKey` if the secret is shorter than `KeyPolicy::default()` allows
+    pub fn from_secret_vec(secret: impl Into<Zeroizing<Vec<u8>>>) -> Result<Self, KeyError> {
+        Self::from_secret_vec_with_policy(secret, &KeyPolicy::default())
+    }
+
+    /// Creates a key from an owned shared secret, checking its length against a policy other than the default
+    ///
+    /// # Arguments
+    /// * `secret` - The shared secret, as a `Vec<u8>` or `Zeroizing<Vec<u8>>`
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()` to accept shorter secrets
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, KeyError>` - The key, or `WeakKey` if the secret is shorter than the policy allows
+    pub fn from_secret_vec_with_policy(secret: impl Into<Zeroizing<Vec<u8>>>, policy: &KeyPolicy) -> Result<Self, KeyError> {
+        Self::checked(DecodingKeyKind::Hmac(secret.into()), policy)
+    }
+
+    /// Reads the key given to `verify` as a string, in the format expected by the algorithm
+    ///
+    /// A PEM or JWK key is never read as a shared secret, as a public key would let anyone forge tokens.
+    pub(crate) fn for_algorithm(key: &str, algorithm: &Algorithm) -> Result<Self, KeyError> {
+        match algorithm {
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512 => Self::from_rsa_pem(key),
+            Algorithm::ES256 | Algorithm::ES384 | Algorithm::ES512 | Algorithm::ES256K => Self::from_ec_pem(key),
+            Algorithm::EdDSA if key.trim_start().starts_with('{') => Self::from_okp_jwk(key),
+            Algorithm::EdDSA => Self::from_ed_pem(key),
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 if is_key_document(key) => Err(KeyError::IncompatibleKey(format!("{} requires a shared secret, not a PEM or JWK key", algorithm))),
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => Self::checked_for(DecodingKeyKind::Hmac(Zeroizing::new(key.as_bytes().to_vec())), &KeyPolicy::default(), Some(algorithm)),
+            Algorithm::None => Err(KeyError::IncompatibleKey("Unsecured tokens do not use a key".to_string())),
+        }
+    }
+
+    /// Reads a public key of any supported type from SPKI (`PUBLIC KEY`), PKCS#1 (`RSA PUBLIC KEY`) or X.509 certificate (`CERTIFICATE`) PEM
+    pub(crate) fn from_public_pem(pem: &str) -> Result<Self, KeyError> {
+        let (format, document) = KeyFormat::read(pem)?;
+        let kind = match format {
+            KeyFormat::Spki => read_spki(document.as_bytes())?,
+            KeyFormat::Pkcs1Public => read_rsa(format, document.as_bytes())?,
+            KeyFormat::Certificate => read_certificate(format, document.as_bytes())?,
+            format => return Err(wrong_format("a public key or certificate", format)),
+        };
+        Self::checked(kind, &KeyPolicy::default())
+    }
+
+    /// Wraps a key that was read, after checking it against a policy
+    pub(crate) fn checked(kind: DecodingKeyKind, policy: &KeyPolicy) -> Result<Self, KeyError> {
+        Self::checked_for(kind, policy, None)
+    }
+
+    /// Checks the key against the policy, and an HMAC secret against the algorithm it will be used with if that is known
+    fn checked_for(kind: DecodingKeyKind, policy: &KeyPolicy, algorithm: Option<&Algorithm>) -> Result<Self, KeyError> {
+        match &kind {
+            DecodingKeyKind::Rsa(public_key) => policy.check_rsa(public_key)?,
+            DecodingKeyKind::Hmac(secret) => policy.check_hmac(secret, algorithm)?,
+            _ => {}
+        }
+        Ok(DecodingKey {
+            hmac_hash_length: policy.hmac_hash_length,
+            ..Self::new(kind)
+        })
+    }
+
+    pub(crate) fn new(kind: DecodingKeyKind) -> Self {
+        DecodingKey {
+            kind: Arc::new(kind),
+            hmac_hash_length: true,
+        }
+    }
+}
+
+impl Debug for DecodingKey {
+    /// Shows the type of key, never the key material as it may be a shared secret
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecodingKey")
+            .field("type", &self.kind.name())
+            .finish_non_exhaustive()
+    }
+}
+
+impl DecodingKeyKind {
+    /// The name of the type of key, shown in place of the key material
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            DecodingKeyKind::Rsa(_) => "RSA",
+            DecodingKeyKind::P256(_) => "EC P-256",
+            DecodingKeyKind::P384(_) => "EC P-384",
+            DecodingKeyKind::P521(_) => "EC P-521",
+            DecodingKeyKind::Secp256k1(_) => "EC secp256k1",
+            DecodingKeyKind::Ed25519(_) => "Ed25519",
+            DecodingKeyKind::Ed448(_) => "Ed448",
+            DecodingKeyKind::Hmac(_) => "HMAC secret",
+        }
+    }
+}
+
+/// Reads the public key from certificate DER, the type of key is read from the algorithm identifier
+fn read_certificate(format: KeyFormat, der: &[u8]) -> Result<DecodingKeyKind, KeyError> {
+    if format != KeyFormat::Certificate {
+        return Err(wrong_format("an X.509 certificate", format));
+    }
+
+    read_spki(&read_certificate_key(der)?)
+}
+
+/// Reads a public key of any supported type from SPKI DER, the type of key is read from the algorithm identifier
+pub(crate) fn read_spki(der: &[u8]) -> Result<DecodingKeyKind, KeyError> {
+    let public_key_info = SubjectPublicKeyInfoRef::try_from(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+    match public_key_info.algorithm.oid {
+        RSA_OID => read_rsa_spki(der).map(DecodingKeyKind::Rsa),
+        EC_OID => read_ec_spki(der),
+        ED25519_OID | ED448_OID => read_ed_spki(der),
+        oid => Err(KeyError::InvalidKey(format!("Unsupported key type {}", oid))),
+    }
+}
+
+/// Reads an RSA public key from SPKI, PKCS#1 or certificate DER
+fn read_rsa(format: KeyFormat, der: &[u8]) -> Result<DecodingKeyKind, KeyError> {
+    let public_key = match format {
+        KeyFormat::Spki => read_rsa_spki(der)?,
+        KeyFormat::Pkcs1Public => RsaPublicKey::from_pkcs1_der(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?,
+        KeyFormat::Certificate => read_rsa_spki(&read_certificate_key(der)?)?,
+        format => return Err(wrong_format("an RSA public key", format)),
+    };
+    Ok(DecodingKeyKind::Rsa(public_key))
+}
+
+/// Reads an EC public key from SPKI or certificate DER
+fn read_ec(format: KeyFormat, der: &[u8]) -> Result<DecodingKeyKind, KeyError> {
+    match format {
+        KeyFormat::Spki => read_ec_spki(der),
+        KeyFormat::Certificate => read_ec_spki(&read_certificate_key(der)?),
+        format => Err(wrong_format("an EC public key", format)),
+    }
+}
+
+/// Reads an Ed25519 or Ed448 public key from SPKI or certificate DER
+fn read_ed(format: KeyFormat, der: &[u8]) -> Result<DecodingKeyKind, KeyError> {
+    match format {
+        KeyFormat::Spki => read_ed_spki(der),
+        KeyFormat::Certificate => read_ed_spki(&read_certificate_key(der)?),
+        format => Err(wrong_format("an Ed25519 or Ed448 public key", format)),
+    }
+}
+
+/// Reads an RSA public key from SPKI DER, checking it is an RSA key first
+fn read_rsa_spki(der: &[u8]) -> Result<RsaPublicKey, KeyError> {
+    let public_key_info = SubjectPublicKeyInfoRef::try_from(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+    if public_key_info.algorithm.oid != RSA_OID {
+        return Err(wrong_format("an RSA public key", format!("an SPKI {} public key", key_type_name(public_key_info.algorithm.oid))));
+    }
+    RsaPublicKey::from_public_key_der(der).map_err(|err| KeyError::InvalidKey(err.to_string()))
+}
+
+/// Reads an EC public key from SPKI DER, the curve is read from the algorithm parameters
+fn read_ec_spki(der: &[u8]) -> Result<DecodingKeyKind, KeyError> {
+    let public_key_info = SubjectPublicKeyInfoRef::try_from(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+    if public_key_info.algorithm.oid != EC_OID {
+        return Err(wrong_format("an EC public key", format!("an SPKI {} public key", key_type_name(public_key_info.algorithm.oid))));
+    }
+    let oid = public_key_info.algorithm.parameters_oid().map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+    let curve = EcCurve::from_oid(oid).ok_or_else(|| KeyError::InvalidKey(format!("Unsupported curve {}", oid)))?;
+
+    let invalid = |err: pkcs8::spki::Error| KeyError::InvalidKey(err.to_string());
+    let kind = match curve {
+        EcCurve::P256 => DecodingKeyKind::P256(p256::ecdsa::VerifyingKey::from_public_key_der(der).map_err(invalid)?),
+        EcCurve::P384 => DecodingKeyKind::P384(p384::ecdsa::VerifyingKey::from_public_key_der(der).map_err(invalid)?),
+        EcCurve::P521 => {
+            let public_key = p521::PublicKey::from_public_key_der(der).map_err(invalid)?;
+            DecodingKeyKind::P521(p521::ecdsa::VerifyingKey::from_affine(*public_key.as_affine()).map_err(|err| KeyError::InvalidKey(err.to_string()))?)
+        }
+        EcCurve::Secp256k1 => DecodingKeyKind::Secp256k1(k256::ecdsa::VerifyingKey::from_public_key_der(der).map_err(invalid)?),
+    };
+    Ok(kind)
+}
+
+/// Reads an Ed25519 or Ed448 public key from SPKI DER
+fn read_ed_spki(der: &[u8]) -> Result<DecodingKeyKind, KeyError> {
+    let public_key_info = SubjectPublicKeyInfoRef::try_from(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+
+    match public_key_info.algorithm.oid {
+        ED25519_OID => ed25519_dalek::VerifyingKey::from_public_key_der(der)
+            .map(DecodingKeyKind::Ed25519)
+            .map_err(|err| KeyError::InvalidKey(err.to_string())),
+        ED448_OID => ed448_goldilocks_plus::VerifyingKey::from_public_key_der(der)
+            .map(DecodingKeyKind::Ed448)
+            .map_err(|err| KeyError::InvalidKey(err.to_string())),
+        oid => Err(wrong_format("an Ed25519 or Ed448 public key", format!("an SPKI {} public key", key_type_name(oid)))),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,755 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use pkcs8::der::asn1::OctetStringRef;
+use pkcs8::der::pem::LineEnding;
+use pkcs8::der::{Decode, SecretDocument};
+use pkcs8::{pkcs5, pkcs5::pbes2, DecodePrivateKey, EncodePrivateKey, EncryptedPrivateKeyInfo, PrivateKeyInfo};
+use rsa::pkcs1::{DecodeRsaPrivateKey, EncodeRsaPrivateKey};
+use rsa::rand_core::{OsRng, RngCore};
+use rsa::RsaPrivateKey;
+use zeroize::Zeroizing;
+
+use crate::model::header::Algorithm;
+use crate::model::jwk::{Jwk, JwkKey};
+
+use super::decoding_key::DecodingKey;
+use super::jwk;
+use super::policy::{hmac_length, KeyPolicy};
+use super::{key_type_name, wrong_format, EcCurve, KeyError, KeyFormat, EC_OID, ED25519_OID, ED448_OID, RSA_OID};
+
+/// The PBKDF2 iterations used when exporting encrypted keys, the OWASP recommendation for PBKDF2-HMAC-SHA256
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// A private key or shared secret used to sign tokens
+///
+/// The key is read once and can then be used to sign any number of tokens.
+/// Cloning is cheap as the key is shared, and it can be sent between threads.
+/// The key material is wiped from memory when the last clone is dropped, and is never shown by `Debug`.
+/// Shared secrets are held in `Zeroizing`, the other private keys rely on the wiping `Drop` of the crate that implements them.
+///
+/// # Example
+/// ```rust,no_run
+/// use super_simple_jwt::{sign_with_key, Algorithm, EncodingKey, Header};
+/// # #[derive(serde::Serialize, serde::Deserialize, Clone)]
+/// # struct Claims { exp: usize, sub: String }
+/// # let claims = Claims { exp: 0, sub: String::new() };
+///
+/// let pem = std::fs::read_to_string("private.pem").unwrap();
+/// let key = EncodingKey::from_rsa_pem(&pem).unwrap();
+///
+/// let token = sign_with_key(&Header::new(Algorithm::RS256), &claims, &key).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct EncodingKey {
+    pub(crate) kind: Arc<EncodingKeyKind>,
+    kid: Option<String>,
+    /// Whether an HMAC secret must be as long as the hash output, from the policy it was read with
+    pub(crate) hmac_hash_length: bool,
+}
+
+/// The parsed key, which differs for each key type
+///
+/// It has no `Drop` of its own: the HMAC secret is wiped by `Zeroizing`, and the other keys are wiped by the `Drop`
+/// of their types in the `rsa`, `ecdsa`, `ed25519-dalek` and `ed448-goldilocks-plus` crates.
+pub(crate) enum EncodingKeyKind {
+    Rsa(Box<RsaPrivateKey>),
+    P256(p256::ecdsa::SigningKey),
+    P384(p384::ecdsa::SigningKey),
+    P521(p521::ecdsa::SigningKey),
+    Secp256k1(k256::ecdsa::SigningKey),
+    Ed25519(Box<ed25519_dalek::SigningKey>),
+    Ed448(Box<ed448_goldilocks_plus::SigningKey>),
+    Hmac(Zeroizing<Vec<u8>>),
+}
+
+impl EncodingKey {
+
+    /// Reads an RSA private key in PKCS#1 (`RSA PRIVATE KEY`) or PKCS#8 (`PRIVATE KEY`) PEM format
+    ///
+    /// The format is detected from the PEM block.
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded private key
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if it could not be read or is weaker than `KeyPolicy::default()`
+    pub fn from_rsa_pem(pem: &str) -> Result<Self, KeyError> {
+        Self::from_rsa_pem_with_policy(pem, &KeyPolicy::default())
+    }
+
+    /// Reads an RSA private key in PKCS#1 or PKCS#8 PEM format, accepting keys that meet a policy other than the default
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded private key
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()`
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if it could not be read or is weaker than the policy
+    pub fn from_rsa_pem_with_policy(pem: &str, policy: &KeyPolicy) -> Result<Self, KeyError> {
+        let (format, document) = KeyFormat::read(pem)?;
+        Self::checked(read_rsa(format, document.as_bytes())?, policy)
+    }
+
+    /// Reads an RSA private key in PKCS#1 or PKCS#8 DER format
+    ///
+    /// The format is detected from the structure of the DER.
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded private key
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if it could not be read or is weaker than `KeyPolicy::default()`
+    pub fn from_rsa_der(der: &[u8]) -> Result<Self, KeyError> {
+        Self::from_rsa_der_with_policy(der, &KeyPolicy::default())
+    }
+
+    /// Reads an RSA private key in PKCS#1 or PKCS#8 DER format, accepting keys that meet a policy other than the default
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded private key
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()`
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if it could not be read or is weaker than the policy
+    pub fn from_rsa_der_with_policy(der: &[u8], policy: &KeyPolicy) -> Result<Self, KeyError> {
+        Self::checked(read_rsa(KeyFormat::detect(der)?, der)?, policy)
+    }
+
+    /// Reads an EC private key in SEC1 (`EC PRIVATE KEY`) or PKCS#8 (`PRIVATE KEY`) PEM format
+    ///
+    /// The curve is read from the key, which must be one of P-256, P-384, P-521 or secp256k1.
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded private key
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if it could not be read
+    pub fn from_ec_pem(pem: &str) -> Result<Self, KeyError> {
+        let (format, document) = KeyFormat::read(pem)?;
+        read_ec(format, document.as_bytes(), None).map(Self::new)
+    }
+
+    /// Reads an EC private key in SEC1 or PKCS#8 DER format
+    ///
+    /// The format is detected from the structure of the DER, and the curve is read from the key.
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded private key
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if it could not be read
+    pub fn from_ec_der(der: &[u8]) -> Result<Self, KeyError> {
+        read_ec(KeyFormat::detect(der)?, der, None).map(Self::new)
+    }
+
+    /// Reads an Ed25519 or Ed448 private key in PKCS#8 PEM format
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded private key
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if it could not be read
+    pub fn from_ed_pem(pem: &str) -> Result<Self, KeyError> {
+        let (format, document) = KeyFormat::read(pem)?;
+        read_ed(format, document.as_bytes()).map(Self::new)
+    }
+
+    /// Reads an Ed25519 or Ed448 private key in PKCS#8 DER format
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded private key
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if it could not be read
+    pub fn from_ed_der(der: &[u8]) -> Result<Self, KeyError> {
+        read_ed(KeyFormat::detect(der)?, der).map(Self::new)
+    }
+
+    /// Reads an Ed25519 or Ed448 private key from an OKP JWK, as defined in RFC 8037
+    ///
+    /// # Arguments
+    /// * `jwk` - The JWK as a JSON string, which must include the private key `d`
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if it could not be read
+    pub fn from_okp_jwk(jwk: &str) -> Result<Self, KeyError> {
+        let jwk: Jwk = serde_json::from_str(jwk).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+        if !matches!(jwk.key, JwkKey::Okp { .. }) {
+            return Err(KeyError::InvalidKey("JWK must have a 'kty' of 'OKP'".to_string()));
+        }
+        Self::from_jwk(&jwk)
+    }
+
+    /// Reads a private key or shared secret from a JWK of any supported key type
+    ///
+    /// RSA, EC and OKP JWKs must include the private key `d`, `oct` JWKs are read as a shared secret for the HMAC algorithms.
+    /// If the JWK has a `use` or `key_ops` member, it must allow signing.
+    ///
+    /// # Arguments
+    /// * `jwk` - The JWK
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if it could not be read or is weaker than `KeyPolicy::default()`
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self, KeyError> {
+        Self::from_jwk_with_policy(jwk, &KeyPolicy::default())
+    }
+
+    /// Reads a private key or shared secret from a JWK, accepting keys that meet a policy other than the default
+    ///
+    /// # Arguments
+    /// * `jwk` - The JWK
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()`
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if it could not be read or is weaker than the policy
+    pub fn from_jwk_with_policy(jwk: &Jwk, policy: &KeyPolicy) -> Result<Self, KeyError> {
+        if !jwk.allows("sign") {
+            return Err(KeyError::IncompatibleKey("JWK 'use' or 'key_ops' does not allow signing".to_string()));
+        }
+        // The secret of an `oct` JWK that names its algorithm must be long enough for it
+        let algorithm = jwk.alg.as_deref().and_then(|alg| alg.parse::<Algorithm>().ok());
+        Self::checked_for(jwk::read_private(&jwk.key)?, policy, algorithm.as_ref())
+    }
+
+    /// Exports the key as a private JWK, or an `oct` JWK for a shared secret
+    ///
+    /// Only the key material and the `kid` of the key are set, the other members can be set on the returned JWK.
+    ///
+    /// # Returns
+    /// * `Result<Jwk, KeyError>` - The JWK, or an error if the key cannot be exported
+    pub fn to_jwk(&self) -> Result<Jwk, KeyError> {
+        let mut jwk = Jwk::new(jwk::write_private(&self.kind)?);
+        jwk.kid = self.kid.clone();
+        Ok(jwk)
+    }
+
+    /// Exports the public half of the key as a JWK, to publish for verifiers
+    ///
+    /// # Returns
+    /// * `Result<Jwk, KeyError>` - The JWK, or `IncompatibleKey` for a shared secret, which has no public half
+    pub fn to_public_jwk(&self) -> Result<Jwk, KeyError> {
+        if matches!(self.kind.as_ref(), EncodingKeyKind::Hmac(_)) {
+            return Err(KeyError::IncompatibleKey("Shared secrets have no public key".to_string()));
+        }
+        let mut jwk = Jwk::new(jwk::write_public(&jwk::public_kind(&self.kind)));
+        jwk.kid = self.kid.clone();
+        Ok(jwk)
+    }
+
+    /// Computes the RFC 7638 SHA-256 thumbprint of the public key, or of the `oct` JWK for a shared secret
+    ///
+    /// # Returns
+    /// * `String` - The base64url encoded thumbprint
+    pub fn thumbprint(&self) -> String {
+        Jwk::new(jwk::write_public(&jwk::public_kind(&self.kind))).thumbprint()
+    }
+
+    /// The RFC 7638 SHA-256 thumbprint of the public key as an RFC 9278 URI
+    ///
+    /// # Returns
+    /// * `String` - The URI, `urn:ietf:params:oauth:jwk-thumbprint:sha-256:` followed by the thumbprint
+    pub fn thumbprint_uri(&self) -> String {
+        Jwk::new(jwk::write_public(&jwk::public_kind(&self.kind))).thumbprint_uri()
+    }
+
+    /// Sets the key ID, which `sign_with_key` puts in the `kid` of tokens whose header has none
+    ///
+    /// # Arguments
+    /// * `kid` - The key ID
+    ///
+    /// # Returns
+    /// * `EncodingKey` - The key with the key ID
+    pub fn with_kid(mut self, kid: impl Into<String>) -> Self {
+        self.kid = Some(kid.into());
+        self
+    }
+
+    /// Sets the key ID to the thumbprint of the key, so `sign_with_key` puts it in the `kid` of tokens whose header has none
+    ///
+    /// # Returns
+    /// * `EncodingKey` - The key with its thumbprint as the key ID
+    pub fn with_thumbprint_kid(self) -> Self {
+        let thumbprint = self.thumbprint();
+        self.with_kid(thumbprint)
+    }
+
+    /// The key ID, if one was set
+    pub fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
+    /// Creates a key from a shared secret, for use with the HMAC algorithms
+    ///
+    /// The secret must be at least 32 bytes, which is checked here. It must also be at least as long as the output of the hash
+    /// function of the algorithm, e.g. 48 bytes for `HS384`, which is checked when signing.
+    ///
+    /// # Arguments
+    /// * `secret` - The shared secret
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or `WeakKey` if the secret is shorter than `KeyPolicy::default()` allows
+    pub fn from_secret(secret: &[u8]) -> Result<Self, KeyError> {
+        Self::from_secret_with_policy(secret, &KeyPolicy::default())
+    }
+
+    /// Creates a key from a shared secret, checking its length against a policy other than the default
+    ///
+    /// # Arguments
+    /// * `secret` - The shared secret
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()` to accept shorter secrets
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or `WeakKey` if the secret is shorter than the policy allows
+    pub fn from_secret_with_policy(secret: &[u8], policy: &KeyPolicy) -> Result<Self, KeyError> {
+        Self::from_secret_vec_with_policy(secret.to_vec(), policy)
+    }
+
+    /// Creates a key from a shared secret, taking ownership of it so no copy is left behind
+    ///
+    /// The buffer is moved into the key without copying, and wiped when the key is dropped.
+    ///
+    /// # Arguments
+    /// * `secret` - The shared secret, as a `Vec<u8>` or `Zeroizing<Vec<u8>>`
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or `WeakKey` if the secret is shorter than `KeyPolicy::default()` allows
+    pub fn from_secret_vec(secret: impl Into<Zeroizing<Vec<u8>>>) -> Result<Self, KeyError> {
+        Self::from_secret_vec_with_policy(secret, &KeyPolicy::default())
+    }
+
+    /// Creates a key from an owned shared secret, checking its length against a policy other than the default
+    ///
+    /// # Arguments
+    /// * `secret` - The shared secret, as a `Vec<u8>` or `Zeroizing<Vec<u8>>`
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()` to accept shorter secrets
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or `WeakKey` if the secret is shorter than the policy allows
+    pub fn from_secret_vec_with_policy(secret: impl Into<Zeroizing<Vec<u8>>>, policy: &KeyPolicy) -> Result<Self, KeyError> {
+        Self::checked(EncodingKeyKind::Hmac(secret.into()), policy)
+    }
+
+    /// Reads a key in the format `sign` expects for the algorithm, taking ownership of the string so it is wiped once read
+    ///
+    /// This is the owned counterpart of the `&str` key given to `sign`: a PEM private key, an OKP JWK for `EdDSA`,
+    /// or the shared secret itself for the HMAC algorithms.
+    ///
+    /// # Arguments
+    /// * `key` - The key, as a `String` or `Zeroizing<String>`
+    /// * `algorithm` - The algorithm the key will be used with
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if it could not be read
+    pub fn from_owned_key(key: impl Into<Zeroizing<String>>, algorithm: &Algorithm) -> Result<Self, KeyError> {
+        Self::for_algorithm(&key.into(), algorithm)
+    }
+
+    /// Generates a new key for an algorithm, with its thumbprint as the key ID
+    ///
+    /// RSA keys are 2048 bits, use `generate_rsa` for another size. ECDSA keys are on the curve of the algorithm,
+    /// `EdDSA` keys are Ed25519 and HMAC secrets are as long as the output of the hash function.
+    ///
+    /// # Arguments
+    /// * `algorithm` - The algorithm the key will be used with
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if the key could not be generated
+    ///
+    /// # Example
+    /// ```rust
+    /// use super_simple_jwt::{Algorithm, EncodingKey};
+    ///
+    /// let key = EncodingKey::generate(&Algorithm::ES256).unwrap();
+    /// let private_pem = key.to_pkcs8_pem().unwrap();
+    /// let public_jwk = key.to_public_jwk().unwrap();
+    /// ```
+    pub fn generate(algorithm: &Algorithm) -> Result<Self, KeyError> {
+        let kind = match algorithm {
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512 => return Self::generate_rsa(2048),
+            Algorithm::ES256 => EncodingKeyKind::P256(p256::ecdsa::SigningKey::random(&mut OsRng)),
+            Algorithm::ES384 => EncodingKeyKind::P384(p384::ecdsa::SigningKey::random(&mut OsRng)),
+            Algorithm::ES512 => EncodingKeyKind::P521(p521::ecdsa::SigningKey::random(&mut OsRng)),
+            Algorithm::ES256K => EncodingKeyKind::Secp256k1(k256::ecdsa::SigningKey::random(&mut OsRng)),
+            Algorithm::EdDSA => {
+                let mut secret_key = Zeroizing::new([0u8; ed25519_dalek::SECRET_KEY_LENGTH]);
+                OsRng.fill_bytes(secret_key.as_mut());
+                EncodingKeyKind::Ed25519(Box::new(ed25519_dalek::SigningKey::from_bytes(&secret_key)))
+            }
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                let mut secret = Zeroizing::new(vec![0u8; hmac_length(algorithm)]);
+                OsRng.fill_bytes(&mut secret);
+                EncodingKeyKind::Hmac(secret)
+            }
+            Algorithm::None => return Err(KeyError::IncompatibleKey("Unsecured tokens do not use a key".to_string())),
+        };
+        Ok(Self::new(kind).with_thumbprint_kid())
+    }
+
+    /// Generates a new RSA key with a modulus of `bits` bits, with its thumbprint as the key ID
+    ///
+    /// # Arguments
+    /// * `bits` - The size of the modulus, e.g. 2048, 3072 or 4096
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or an error if the key could not be generated or is smaller than `KeyPolicy::default()` allows
+    pub fn generate_rsa(bits: usize) -> Result<Self, KeyError> {
+        let policy = KeyPolicy::default();
+        if bits < policy.min_rsa_bits {
+            return Err(KeyError::WeakKey(format!("The RSA modulus would be {} bits, at least {} bits are required", bits, policy.min_rsa_bits)));
+        }
+        let private_key = RsaPrivateKey::new(&mut OsRng, bits).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+        Ok(Self::new(EncodingKeyKind::Rsa(Box::new(private_key))).with_thumbprint_kid())
+    }
+
+    /// Reads a private key of any supported type in encrypted PKCS#8 (`ENCRYPTED PRIVATE KEY`) PEM format
+    ///
+    /// The key is decrypted with the passphrase using PBES2, as defined in RFC 8018.
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded encrypted private key
+    /// * `passphrase` - The passphrase the key was encrypted with
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or `WrongPassphrase` if the passphrase is wrong, or an error if the key is weaker than `KeyPolicy::default()`
+    pub fn from_encrypted_pem(pem: &str, passphrase: &[u8]) -> Result<Self, KeyError> {
+        Self::from_encrypted_pem_with_policy(pem, passphrase, &KeyPolicy::default())
+    }
+
+    /// Reads a private key of any supported type in encrypted PKCS#8 PEM format, accepting keys that meet a policy other than the default
+    ///
+    /// # Arguments
+    /// * `pem` - The PEM encoded encrypted private key
+    /// * `passphrase` - The passphrase the key was encrypted with
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()`
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or `WrongPassphrase` if the passphrase is wrong, or an error if the key is weaker than the policy
+    pub fn from_encrypted_pem_with_policy(pem: &str, passphrase: &[u8], policy: &KeyPolicy) -> Result<Self, KeyError> {
+        let (format, document) = KeyFormat::read(pem)?;
+        Self::checked(read_encrypted(format, document.as_bytes(), passphrase)?, policy)
+    }
+
+    /// Reads a private key of any supported type in encrypted PKCS#8 DER format
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded encrypted private key
+    /// * `passphrase` - The passphrase the key was encrypted with
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or `WrongPassphrase` if the passphrase is wrong, or an error if the key is weaker than `KeyPolicy::default()`
+    pub fn from_encrypted_der(der: &[u8], passphrase: &[u8]) -> Result<Self, KeyError> {
+        Self::from_encrypted_der_with_policy(der, passphrase, &KeyPolicy::default())
+    }
+
+    /// Reads a private key of any supported type in encrypted PKCS#8 DER format, accepting keys that meet a policy other than the default
+    ///
+    /// # Arguments
+    /// * `der` - The DER encoded encrypted private key
+    /// * `passphrase` - The passphrase the key was encrypted with
+    /// * `policy` - The minimum strength of the key, e.g. `KeyPolicy::legacy()`
+    ///
+    /// # Returns
+    /// * `Result<EncodingKey, KeyError>` - The key, or `WrongPassphrase` if the passphrase is wrong, or an error if the key is weaker than the policy
+    pub fn from_encrypted_der_with_policy(der: &[u8], passphrase: &[u8], policy: &KeyPolicy) -> Result<Self, KeyError> {
+        Self::checked(read_encrypted(KeyFormat::detect(der)?, der, passphrase)?, policy)
+    }
+
+    /// Exports the key in unencrypted PKCS#8 (`PRIVATE KEY`) PEM format
+    ///
+    /// # Returns
+    /// * `Result<Zeroizing<String>, KeyError>` - The PEM encoded key, wiped when dropped, or an error if the key cannot be exported, e.g. a shared secret
+    pub fn to_pkcs8_pem(&self) -> Result<Zeroizing<String>, KeyError> {
+        self.pkcs8_document()?.to_pem("PRIVATE KEY", LineEnding::LF).map_err(|err| KeyError::InvalidKey(err.to_string()))
+    }
+
+    /// Exports the key in unencrypted PKCS#8 DER format
+    ///
+    /// # Returns
+    /// * `Result<Zeroizing<Vec<u8>>, KeyError>` - The DER encoded key, wiped when dropped, or an error if the key cannot be exported, e.g. a shared secret
+    pub fn to_pkcs8_der(&self) -> Result<Zeroizing<Vec<u8>>, KeyError> {
+        Ok(self.pkcs8_document()?.to_bytes())
+    }
+
+    /// Exports an RSA key in PKCS#1 (`RSA PRIVATE KEY`) PEM format
+    ///
+    /// # Returns
+    /// * `Result<Zeroizing<String>, KeyError>` - The PEM encoded key, wiped when dropped, or `IncompatibleKey` if it is not an RSA key
+    pub fn to_pkcs1_pem(&self) -> Result<Zeroizing<String>, KeyError> {
+        let EncodingKeyKind::Rsa(private_key) = self.kind.as_ref() else {
+            return Err(KeyError::IncompatibleKey("Only RSA keys can be exported as PKCS#1".to_string()));
+        };
+        private_key.to_pkcs1_pem(LineEnding::LF).map_err(|err| KeyError::InvalidKey(err.to_string()))
+    }
+
+    /// Exports an RSA key in PKCS#1 DER format
+    ///
+    /// # Returns
+    /// * `Result<Zeroizing<Vec<u8>>, KeyError>` - The DER encoded key, wiped when dropped, or `IncompatibleKey` if it is not an RSA key
+    pub fn to_pkcs1_der(&self) -> Result<Zeroizing<Vec<u8>>, KeyError> {
+        let EncodingKeyKind::Rsa(private_key) = self.kind.as_ref() else {
+            return Err(KeyError::IncompatibleKey("Only RSA keys can be exported as PKCS#1".to_string()));
+        };
+        let document = private_key.to_pkcs1_der().map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+        Ok(document.to_bytes())
+    }
+
+    /// Returns the key for verifying the tokens this key signs, the public key or the shared secret itself
+    ///
+    /// # Returns
+    /// * `DecodingKey` - The key to verify tokens with
+    pub fn to_decoding_key(&self) -> DecodingKey {
+        DecodingKey {
+            hmac_hash_length: self.hmac_hash_length,
+            ..DecodingKey::new(jwk::public_kind(&self.kind))
+        }
+    }
+
+    /// Exports the key in encrypted PKCS#8 (`ENCRYPTED PRIVATE KEY`) PEM format
+    ///
+    /// The key is encrypted with PBES2, using PBKDF2-HMAC-SHA256 and AES-256-CBC, so it can be read by other tools such as OpenSSL.
+    ///
+    /// # Arguments
+    /// * `passphrase` - The passphrase to encrypt the key with
+    ///
+    /// # Returns
+    /// * `Result<String, KeyError>` - The PEM encoded key, or an error if the key cannot be exported, e.g. a shared secret
+    pub fn to_encrypted_pem(&self, passphrase: &[u8]) -> Result<String, KeyError> {
+        let document = self.encrypt(passphrase)?;
+        let pem = document.to_pem("ENCRYPTED PRIVATE KEY", LineEnding::LF).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+        Ok(pem.to_string())
+    }
+
+    /// Exports the key in encrypted PKCS#8 DER format
+    ///
+    /// # Arguments
+    /// * `passphrase` - The passphrase to encrypt the key with
+    ///
+    /// # Returns
+    /// * `Result<Vec<u8>, KeyError>` - The DER encoded key, or an error if the key cannot be exported, e.g. a shared secret
+    pub fn to_encrypted_der(&self, passphrase: &[u8]) -> Result<Vec<u8>, KeyError> {
+        Ok(self.encrypt(passphrase)?.as_bytes().to_vec())
+    }
+
+    /// Reads the key given to `sign` as a string, in the format expected by the algorithm
+    pub(crate) fn for_algorithm(key: &str, algorithm: &Algorithm) -> Result<Self, KeyError> {
+        match algorithm {
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512 => Self::from_rsa_pem(key),
+            Algorithm::ES256 | Algorithm::ES384 | Algorithm::ES512 | Algorithm::ES256K => {
+                let (format, document) = KeyFormat::read(key)?;
+                read_ec(format, document.as_bytes(), EcCurve::for_algorithm(algorithm)).map(Self::new)
+            },
+            Algorithm::EdDSA if key.trim_start().starts_with('{') => Self::from_okp_jwk(key),
+            Algorithm::EdDSA => Self::from_ed_pem(key),
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => Self::checked_for(EncodingKeyKind::Hmac(Zeroizing::new(key.as_bytes().to_vec())), &KeyPolicy::default(), Some(algorithm)),
+            Algorithm::None => Err(KeyError::IncompatibleKey("Unsecured tokens do not use a key".to_string())),
+        }
+    }
+
+    /// Encodes the key as PKCS#8 DER
+    fn pkcs8_document(&self) -> Result<SecretDocument, KeyError> {
+        let document = match self.kind.as_ref() {
+            EncodingKeyKind::Rsa(private_key) => private_key.to_pkcs8_der(),
+            EncodingKeyKind::P256(signing_key) => signing_key.to_pkcs8_der(),
+            EncodingKeyKind::P384(signing_key) => signing_key.to_pkcs8_der(),
+            EncodingKeyKind::P521(signing_key) => p521::SecretKey::from_bytes(&signing_key.to_bytes()).map_err(|err| KeyError::InvalidKey(err.to_string()))?.to_pkcs8_der(),
+            EncodingKeyKind::Secp256k1(signing_key) => signing_key.to_pkcs8_der(),
+            EncodingKeyKind::Ed25519(signing_key) => signing_key.to_pkcs8_der(),
+            EncodingKeyKind::Ed448(signing_key) => signing_key.to_pkcs8_der(),
+            EncodingKeyKind::Hmac(_) => return Err(KeyError::IncompatibleKey("Shared secrets cannot be exported as PKCS#8".to_string())),
+        };
+        document.map_err(|err| KeyError::InvalidKey(err.to_string()))
+    }
+
+    /// Encodes the key as PKCS#8 DER and encrypts it with a random salt and IV
+    fn encrypt(&self, passphrase: &[u8]) -> Result<SecretDocument, KeyError> {
+        let document = self.pkcs8_document()?;
+        let private_key_info = PrivateKeyInfo::try_from(document.as_bytes()).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+
+        let mut salt = [0u8; 16];
+        let mut iv = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut iv);
+
+        let parameters = pbes2::Parameters::pbkdf2_sha256_aes256cbc(PBKDF2_ITERATIONS, &salt, &iv).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+        private_key_info.encrypt_with_params(parameters, passphrase).map_err(|err| KeyError::InvalidKey(err.to_string()))
+    }
+
+    /// Wraps a key that was read, after checking it against a policy
+    fn checked(kind: EncodingKeyKind, policy: &KeyPolicy) -> Result<Self, KeyError> {
+        Self::checked_for(kind, policy, None)
+    }
+
+    /// Checks the key against the policy, and an HMAC secret against the algorithm it will be used with if that is known
+    fn checked_for(kind: EncodingKeyKind, policy: &KeyPolicy, algorithm: Option<&Algorithm>) -> Result<Self, KeyError> {
+        match &kind {
+            EncodingKeyKind::Rsa(private_key) => policy.check_rsa(private_key.as_ref())?,
+            EncodingKeyKind::Hmac(secret) => policy.check_hmac(secret, algorithm)?,
+            _ => {}
+        }
+        Ok(EncodingKey {
+            hmac_hash_length: policy.hmac_hash_length,
+            ..Self::new(kind)
+        })
+    }
+
+    fn new(kind: EncodingKeyKind) -> Self {
+        EncodingKey {
+            kind: Arc::new(kind),
+            kid: None,
+            hmac_hash_length: true,
+        }
+    }
+}
+
+impl Debug for EncodingKey {
+    /// Shows the type of key and its key ID, never the key material
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncodingKey")
+            .field("type", &self.kind.name())
+            .field("kid", &self.kid)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EncodingKeyKind {
+    /// The name of the type of key, shown in place of the key material
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            EncodingKeyKind::Rsa(_) => "RSA",
+            EncodingKeyKind::P256(_) => "EC P-256",
+            EncodingKeyKind::P384(_) => "EC P-384",
+            EncodingKeyKind::P521(_) => "EC P-521",
+            EncodingKeyKind::Secp256k1(_) => "EC secp256k1",
+            EncodingKeyKind::Ed25519(_) => "Ed25519",
+            EncodingKeyKind::Ed448(_) => "Ed448",
+            EncodingKeyKind::Hmac(_) => "HMAC secret",
+        }
+    }
+}
+
+/// Decrypts an encrypted PKCS#8 key and reads it, the type of key is read from the algorithm identifier
+fn read_encrypted(format: KeyFormat, der: &[u8], passphrase: &[u8]) -> Result<EncodingKeyKind, KeyError> {
+    if format != KeyFormat::EncryptedPkcs8 {
+        return Err(wrong_format("an encrypted PKCS#8 private key", format));
+    }
+
+    let encrypted_private_key_info = EncryptedPrivateKeyInfo::try_from(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+    let document = encrypted_private_key_info.decrypt(passphrase).map_err(|err| match err {
+        // pkcs5 reports bad padding, the usual result of a wrong passphrase, as `EncryptFailed`
+        pkcs8::Error::EncryptedPrivateKey(pkcs5::Error::DecryptFailed | pkcs5::Error::EncryptFailed) => KeyError::WrongPassphrase,
+        // The encrypted structure was already parsed, so a DER error comes from garbage that happened to have valid padding
+        pkcs8::Error::Asn1(_) => KeyError::WrongPassphrase,
+        err => KeyError::InvalidKey(err.to_string()),
+    })?;
+
+    // Garbage that still parses as DER will not be a valid key
+    let private_key_info = PrivateKeyInfo::try_from(document.as_bytes()).map_err(|_| KeyError::WrongPassphrase)?;
+    match private_key_info.algorithm.oid {
+        RSA_OID => read_rsa_pkcs8(document.as_bytes()).map(|private_key| EncodingKeyKind::Rsa(Box::new(private_key))),
+        EC_OID => read_ec_pkcs8(document.as_bytes()),
+        ED25519_OID | ED448_OID => read_ed_pkcs8(document.as_bytes()),
+        oid => Err(KeyError::InvalidKey(format!("Unsupported key type {}", oid))),
+    }
+}
+
+/// Reads an RSA private key from PKCS#1 or PKCS#8 DER
+fn read_rsa(format: KeyFormat, der: &[u8]) -> Result<EncodingKeyKind, KeyError> {
+    let private_key = match format {
+        KeyFormat::Pkcs1Private => RsaPrivateKey::from_pkcs1_der(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?,
+        KeyFormat::Pkcs8 => read_rsa_pkcs8(der)?,
+        format => return Err(wrong_format("an RSA private key", format)),
+    };
+    Ok(EncodingKeyKind::Rsa(Box::new(private_key)))
+}
+
+/// Reads an EC private key from SEC1 or PKCS#8 DER
+///
+/// SEC1 keys do not have to name their curve, in which case `expected` is used.
+/// When the key does name its curve and it is not `expected`, the key is still read so the caller can report the mismatch.
+fn read_ec(format: KeyFormat, der: &[u8], expected: Option<EcCurve>) -> Result<EncodingKeyKind, KeyError> {
+    match format {
+        KeyFormat::Sec1 => read_ec_sec1(der, expected),
+        KeyFormat::Pkcs8 => read_ec_pkcs8(der),
+        format => Err(wrong_format("an EC private key", format)),
+    }
+}
+
+/// Reads an Ed25519 or Ed448 private key from PKCS#8 DER
+fn read_ed(format: KeyFormat, der: &[u8]) -> Result<EncodingKeyKind, KeyError> {
+    match format {
+        KeyFormat::Pkcs8 => read_ed_pkcs8(der),
+        format => Err(wrong_format("an Ed25519 or Ed448 private key", format)),
+    }
+}
+
+/// Reads an EC private key from SEC1 DER, using `expected` if the key does not name its curve
+fn read_ec_sec1(der: &[u8], expected: Option<EcCurve>) -> Result<EncodingKeyKind, KeyError> {
+    let ec_private_key = sec1::EcPrivateKey::from_der(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+    let curve = match ec_private_key.parameters.and_then(|parameters| parameters.named_curve()) {
+        Some(oid) => EcCurve::from_oid(oid).ok_or_else(|| KeyError::InvalidKey(format!("Unsupported curve {}", oid)))?,
+        None => expected.ok_or_else(|| KeyError::InvalidKey("SEC1 key does not name its curve".to_string()))?,
+    };
+
+    let invalid = |err: pkcs8::der::Error| KeyError::InvalidKey(err.to_string());
+    let kind = match curve {
+        EcCurve::P256 => EncodingKeyKind::P256(p256::SecretKey::try_from(ec_private_key).map_err(invalid)?.into()),
+        EcCurve::P384 => EncodingKeyKind::P384(p384::SecretKey::try_from(ec_private_key).map_err(invalid)?.into()),
+        EcCurve::P521 => {
+            let secret_key = p521::SecretKey::try_from(ec_private_key).map_err(invalid)?;
+            EncodingKeyKind::P521(p521::ecdsa::SigningKey::from_bytes(&secret_key.to_bytes()).map_err(|err| KeyError::InvalidKey(err.to_string()))?)
+        }
+        EcCurve::Secp256k1 => EncodingKeyKind::Secp256k1(k256::SecretKey::try_from(ec_private_key).map_err(invalid)?.into()),
+    };
+    Ok(kind)
+}
+
+/// Reads an RSA private key from PKCS#8 DER, checking it is an RSA key first
+fn read_rsa_pkcs8(der: &[u8]) -> Result<RsaPrivateKey, KeyError> {
+    let private_key_info = PrivateKeyInfo::try_from(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+    if private_key_info.algorithm.oid != RSA_OID {
+        return Err(wrong_format("an RSA private key", format!("a PKCS#8 {} private key", key_type_name(private_key_info.algorithm.oid))));
+    }
+    RsaPrivateKey::from_pkcs8_der(der).map_err(|err| KeyError::InvalidKey(err.to_string()))
+}
+
+/// Reads an EC private key from PKCS#8 DER, the curve is read from the algorithm parameters
+fn read_ec_pkcs8(der: &[u8]) -> Result<EncodingKeyKind, KeyError> {
+    let private_key_info = PrivateKeyInfo::try_from(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+    if private_key_info.algorithm.oid != EC_OID {
+        return Err(wrong_format("an EC private key", format!("a PKCS#8 {} private key", key_type_name(private_key_info.algorithm.oid))));
+    }
+    let oid = private_key_info.algorithm.parameters_oid().map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+    let curve = EcCurve::from_oid(oid).ok_or_else(|| KeyError::InvalidKey(format!("Unsupported curve {}", oid)))?;
+
+    let invalid = |err: pkcs8::Error| KeyError::InvalidKey(err.to_string());
+    let kind = match curve {
+        EcCurve::P256 => EncodingKeyKind::P256(p256::SecretKey::from_pkcs8_der(der).map_err(invalid)?.into()),
+        EcCurve::P384 => EncodingKeyKind::P384(p384::SecretKey::from_pkcs8_der(der).map_err(invalid)?.into()),
+        EcCurve::P521 => {
+            let secret_key = p521::SecretKey::from_pkcs8_der(der).map_err(invalid)?;
+            EncodingKeyKind::P521(p521::ecdsa::SigningKey::from_bytes(&secret_key.to_bytes()).map_err(|err| KeyError::InvalidKey(err.to_string()))?)
+        }
+        EcCurve::Secp256k1 => EncodingKeyKind::Secp256k1(k256::SecretKey::from_pkcs8_der(der).map_err(invalid)?.into()),
+    };
+    Ok(kind)
+}
+
+/// Reads an Ed25519 or Ed448 private key from PKCS#8 DER
+fn read_ed_pkcs8(der: &[u8]) -> Result<EncodingKeyKind, KeyError> {
+    let private_key_info = PrivateKeyInfo::try_from(der).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+
+    match private_key_info.algorithm.oid {
+        ED25519_OID => ed25519_dalek::SigningKey::from_pkcs8_der(der)
+            .map(|signing_key| EncodingKeyKind::Ed25519(Box::new(signing_key)))
+            .map_err(|err| KeyError::InvalidKey(err.to_string())),
+        ED448_OID => {
+            // RFC 8410 wraps the key in a second OCTET STRING, which ed448_goldilocks_plus does not expect
+            let private_key = OctetStringRef::from_der(private_key_info.private_key).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+            ed448_goldilocks_plus::SigningKey::try_from(private_key.as_bytes())
+                .map(|signing_key| EncodingKeyKind::Ed448(Box::new(signing_key)))
+                .map_err(|err| KeyError::InvalidKey(err.to_string()))
+        }
+        oid => Err(wrong_format("an Ed25519 or Ed448 private key", format!("a PKCS#8 {} private key", key_type_name(oid)))),
+    }
+}
@@ -0,0 +1,239 @@
+use base64::Engine;
+use rsa::traits::{PrivateKeyParts, PublicKeyParts};
+use rsa::{BigUint, RsaPrivateKey, RsaPublicKey};
+use zeroize::Zeroizing;
+
+use crate::decoding::DECODING_ENGINE;
+use crate::encoding::ENCODING_ENGINE;
+use crate::model::header::Algorithm;
+use crate::model::jwk::{Jwk, JwkKey};
+
+use super::decoding_key::DecodingKeyKind;
+use super::encoding_key::EncodingKeyKind;
+use super::{EcCurve, KeyError};
+
+/// Reads a private key or shared secret from the key material of a JWK
+pub(crate) fn read_private(key: &JwkKey) -> Result<EncodingKeyKind, KeyError> {
+    let kind = match key {
+        JwkKey::Rsa { n, e, d, p, q, .. } => {
+            let d = d.as_deref().ok_or_else(missing_private_key)?;
+            let primes = match (p, q) {
+                (Some(p), Some(q)) => vec![read_uint("p", p)?, read_uint("q", q)?],
+                // The primes are recovered from the private exponent
+                _ => Vec::new(),
+            };
+            let private_key = RsaPrivateKey::from_components(read_uint("n", n)?, read_uint("e", e)?, read_uint("d", d)?, primes)
+                .map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+            EncodingKeyKind::Rsa(Box::new(private_key))
+        }
+        JwkKey::Ec { crv, d, .. } => {
+            let d = read_member("d", d.as_deref().ok_or_else(missing_private_key)?)?;
+            let invalid = |err: p256::ecdsa::Error| KeyError::InvalidKey(err.to_string());
+            match read_curve(crv)? {
+                EcCurve::P256 => EncodingKeyKind::P256(p256::ecdsa::SigningKey::from_slice(&d).map_err(invalid)?),
+                EcCurve::P384 => EncodingKeyKind::P384(p384::ecdsa::SigningKey::from_slice(&d).map_err(invalid)?),
+                EcCurve::P521 => EncodingKeyKind::P521(p521::ecdsa::SigningKey::from_slice(&d).map_err(invalid)?),
+                EcCurve::Secp256k1 => EncodingKeyKind::Secp256k1(k256::ecdsa::SigningKey::from_slice(&d).map_err(invalid)?),
+            }
+        }
+        JwkKey::Okp { crv, d, .. } => {
+            let d = read_member("d", d.as_deref().ok_or_else(missing_private_key)?)?;
+            match crv.as_str() {
+                "Ed25519" => {
+                    let d: Zeroizing<[u8; ed25519_dalek::SECRET_KEY_LENGTH]> = Zeroizing::new(d.as_slice().try_into().map_err(|_| KeyError::InvalidKey("Ed25519 private key must be 32 bytes".to_string()))?);
+                    EncodingKeyKind::Ed25519(Box::new(ed25519_dalek::SigningKey::from_bytes(&d)))
+                }
+                "Ed448" => {
+                    let signing_key = ed448_goldilocks_plus::SigningKey::try_from(d.as_slice()).map_err(|_| KeyError::InvalidKey("Ed448 private key must be 57 bytes".to_string()))?;
+                    EncodingKeyKind::Ed448(Box::new(signing_key))
+                }
+                crv => return Err(KeyError::InvalidKey(format!("Unsupported OKP curve {:?}", crv))),
+            }
+        }
+        JwkKey::Oct { k } => EncodingKeyKind::Hmac(read_member("k", k)?),
+    };
+
+    // A JWK holds both halves of the key, make sure they belong together
+    if !matches!(key, JwkKey::Oct { .. }) && write_public(&public_kind(&kind)) != key.without_private() {
+        return Err(KeyError::InvalidKey("JWK public key does not match the private key".to_string()));
+    }
+    Ok(kind)
+}
+
+/// Reads a public key or shared secret from the key material of a JWK, ignoring any private key
+pub(crate) fn read_public(key: &JwkKey) -> Result<DecodingKeyKind, KeyError> {
+    let kind = match key {
+        JwkKey::Rsa { n, e, .. } => {
+            let public_key = RsaPublicKey::new(read_uint("n", n)?, read_uint("e", e)?).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+            DecodingKeyKind::Rsa(public_key)
+        }
+        JwkKey::Ec { crv, x, y, .. } => {
+            let curve = read_curve(crv)?;
+            let (x, y) = (read_member("x", x)?, read_member("y", y)?);
+            if x.len() != coordinate_length(curve) || y.len() != coordinate_length(curve) {
+                return Err(KeyError::InvalidKey(format!("{} coordinates must be {} bytes", curve, coordinate_length(curve))));
+            }
+
+            // Uncompressed SEC1 point
+            let point = [&[0x04], x.as_slice(), y.as_slice()].concat();
+            let invalid = |err: p256::ecdsa::Error| KeyError::InvalidKey(err.to_string());
+            match curve {
+                EcCurve::P256 => DecodingKeyKind::P256(p256::ecdsa::VerifyingKey::from_sec1_bytes(&point).map_err(invalid)?),
+                EcCurve::P384 => DecodingKeyKind::P384(p384::ecdsa::VerifyingKey::from_sec1_bytes(&point).map_err(invalid)?),
+                EcCurve::P521 => DecodingKeyKind::P521(p521::ecdsa::VerifyingKey::from_sec1_bytes(&point).map_err(invalid)?),
+                EcCurve::Secp256k1 => DecodingKeyKind::Secp256k1(k256::ecdsa::VerifyingKey::from_sec1_bytes(&point).map_err(invalid)?),
+            }
+        }
+        JwkKey::Okp { crv, x, .. } => {
+            let x = read_member("x", x)?;
+            match crv.as_str() {
+                "Ed25519" => {
+                    let x: [u8; ed25519_dalek::PUBLIC_KEY_LENGTH] = x.as_slice().try_into().map_err(|_| KeyError::InvalidKey("Ed25519 public key must be 32 bytes".to_string()))?;
+                    DecodingKeyKind::Ed25519(ed25519_dalek::VerifyingKey::from_bytes(&x).map_err(|err| KeyError::InvalidKey(err.to_string()))?)
+                }
+                "Ed448" => {
+                    let x: [u8; ed448_goldilocks_plus::PUBLIC_KEY_LENGTH] = x.as_slice().try_into().map_err(|_| KeyError::InvalidKey("Ed448 public key must be 57 bytes".to_string()))?;
+                    DecodingKeyKind::Ed448(ed448_goldilocks_plus::VerifyingKey::from_bytes(&x).map_err(|err| KeyError::InvalidKey(err.to_string()))?)
+                }
+                crv => return Err(KeyError::InvalidKey(format!("Unsupported OKP curve {:?}", crv))),
+            }
+        }
+        JwkKey::Oct { k } => DecodingKeyKind::Hmac(read_member("k", k)?),
+    };
+    Ok(kind)
+}
+
+/// Writes the key material of a private key or shared secret
+pub(crate) fn write_private(kind: &EncodingKeyKind) -> Result<JwkKey, KeyError> {
+    let d = match kind {
+        EncodingKeyKind::Rsa(private_key) => return write_rsa_private(private_key),
+        EncodingKeyKind::Hmac(secret) => return Ok(JwkKey::Oct { k: write_secret(secret) }),
+        EncodingKeyKind::P256(signing_key) => Zeroizing::new(signing_key.to_bytes().to_vec()),
+        EncodingKeyKind::P384(signing_key) => Zeroizing::new(signing_key.to_bytes().to_vec()),
+        EncodingKeyKind::P521(signing_key) => Zeroizing::new(signing_key.to_bytes().to_vec()),
+        EncodingKeyKind::Secp256k1(signing_key) => Zeroizing::new(signing_key.to_bytes().to_vec()),
+        EncodingKeyKind::Ed25519(signing_key) => Zeroizing::new(signing_key.to_bytes().to_vec()),
+        EncodingKeyKind::Ed448(signing_key) => Zeroizing::new(signing_key.to_bytes().to_vec()),
+    };
+
+    // The public members are the same as for the public key
+    let d = Some(write_secret(&d));
+    let key = match write_public(&public_kind(kind)) {
+        JwkKey::Ec { crv, x, y, .. } => JwkKey::Ec { crv, x, y, d },
+        JwkKey::Okp { crv, x, .. } => JwkKey::Okp { crv, x, d },
+        key => key,
+    };
+    Ok(key)
+}
+
+/// Writes the key material of an RSA private key, including the CRT parameters
+fn write_rsa_private(private_key: &RsaPrivateKey) -> Result<JwkKey, KeyError> {
+    // Keys with more than two primes need the `oth` member, which is not supported
+    let [p, q] = private_key.primes() else {
+        return Err(KeyError::IncompatibleKey("RSA keys with more than two primes cannot be exported as a JWK".to_string()));
+    };
+    Ok(JwkKey::Rsa {
+        n: write_uint(private_key.n()),
+        e: write_uint(private_key.e()),
+        d: Some(write_secret_uint(private_key.d())),
+        p: Some(write_secret_uint(p)),
+        q: Some(write_secret_uint(q)),
+        dp: private_key.dp().map(write_secret_uint),
+        dq: private_key.dq().map(write_secret_uint),
+        qi: private_key.crt_coefficient().map(|qi| write_secret_uint(&qi)),
+    })
+}
+
+/// Writes the key material of a public key or shared secret
+pub(crate) fn write_public(kind: &DecodingKeyKind) -> JwkKey {
+    let ec = |curve: EcCurve, point: &[u8]| {
+        // Uncompressed SEC1 point, `0x04 || x || y`
+        let (x, y) = point[1..].split_at(coordinate_length(curve));
+        JwkKey::Ec { crv: curve.to_string(), x: ENCODING_ENGINE.encode(x), y: ENCODING_ENGINE.encode(y), d: None }
+    };
+    match kind {
+        DecodingKeyKind::Rsa(public_key) => JwkKey::Rsa { n: write_uint(public_key.n()), e: write_uint(public_key.e()), d: None, p: None, q: None, dp: None, dq: None, qi: None },
+        DecodingKeyKind::P256(verifying_key) => ec(EcCurve::P256, verifying_key.to_encoded_point(false).as_bytes()),
+        DecodingKeyKind::P384(verifying_key) => ec(EcCurve::P384, verifying_key.to_encoded_point(false).as_bytes()),
+        DecodingKeyKind::P521(verifying_key) => ec(EcCurve::P521, verifying_key.to_encoded_point(false).as_bytes()),
+        DecodingKeyKind::Secp256k1(verifying_key) => ec(EcCurve::Secp256k1, verifying_key.to_encoded_point(false).as_bytes()),
+        DecodingKeyKind::Ed25519(verifying_key) => JwkKey::Okp { crv: "Ed25519".to_string(), x: ENCODING_ENGINE.encode(verifying_key.as_bytes()), d: None },
+        DecodingKeyKind::Ed448(verifying_key) => JwkKey::Okp { crv: "Ed448".to_string(), x: ENCODING_ENGINE.encode(verifying_key.as_bytes()), d: None },
+        DecodingKeyKind::Hmac(secret) => JwkKey::Oct { k: write_secret(secret) },
+    }
+}
+
+/// The public half of a private key, or the shared secret itself
+pub(crate) fn public_kind(kind: &EncodingKeyKind) -> DecodingKeyKind {
+    match kind {
+        EncodingKeyKind::Rsa(private_key) => DecodingKeyKind::Rsa(private_key.to_public_key()),
+        EncodingKeyKind::P256(signing_key) => DecodingKeyKind::P256(*signing_key.verifying_key()),
+        EncodingKeyKind::P384(signing_key) => DecodingKeyKind::P384(*signing_key.verifying_key()),
+        EncodingKeyKind::P521(signing_key) => DecodingKeyKind::P521(p521::ecdsa::VerifyingKey::from(signing_key)),
+        EncodingKeyKind::Secp256k1(signing_key) => DecodingKeyKind::Secp256k1(*signing_key.verifying_key()),
+        EncodingKeyKind::Ed25519(signing_key) => DecodingKeyKind::Ed25519(signing_key.verifying_key()),
+        EncodingKeyKind::Ed448(signing_key) => DecodingKeyKind::Ed448(signing_key.verifying_key()),
+        EncodingKeyKind::Hmac(secret) => DecodingKeyKind::Hmac(secret.clone()),
+    }
+}
+
+/// The curve named by the `crv` member of an EC JWK
+fn read_curve(crv: &str) -> Result<EcCurve, KeyError> {
+    match crv {
+        "P-256" => Ok(EcCurve::P256),
+        "P-384" => Ok(EcCurve::P384),
+        "P-521" => Ok(EcCurve::P521),
+        "secp256k1" => Ok(EcCurve::Secp256k1),
+        crv => Err(KeyError::InvalidKey(format!("Unsupported EC curve {:?}", crv))),
+    }
+}
+
+/// The length of a coordinate of a point on the curve, which JWKs must use exactly
+fn coordinate_length(curve: EcCurve) -> usize {
+    match curve {
+        EcCurve::P256 | EcCurve::Secp256k1 => 32,
+        EcCurve::P384 => 48,
+        EcCurve::P521 => 66,
+    }
+}
+
+/// Decodes a member, which is wiped when dropped as it may be a private key
+fn read_member(name: &str, value: &str) -> Result<Zeroizing<Vec<u8>>, KeyError> {
+    DECODING_ENGINE.decode(value).map(Zeroizing::new).map_err(|err| KeyError::InvalidKey(format!("JWK member '{}' is not valid base64url: {}", name, err)))
+}
+
+fn read_uint(name: &str, value: &str) -> Result<BigUint, KeyError> {
+    read_member(name, value).map(|bytes| BigUint::from_bytes_be(&bytes))
+}
+
+fn write_uint(value: &BigUint) -> String {
+    ENCODING_ENGINE.encode(value.to_bytes_be())
+}
+
+/// Encodes a private member or shared secret, which is wiped when dropped
+fn write_secret(value: &[u8]) -> Zeroizing<String> {
+    Zeroizing::new(ENCODING_ENGINE.encode(value))
+}
+
+/// Encodes a private integer member, wiping its bytes once encoded
+fn write_secret_uint(value: &BigUint) -> Zeroizing<String> {
+    write_secret(&Zeroizing::new(value.to_bytes_be()))
+}
+
+fn missing_private_key() -> KeyError {
+    KeyError::InvalidKey("JWK is missing the private key 'd'".to_string())
+}
+
+/// Whether a JWK can verify tokens signed with an algorithm, using its `alg` member if set and otherwise its key type and curve
+pub(crate) fn matches_algorithm(jwk: &Jwk, algorithm: &Algorithm) -> bool {
+    if let Some(alg) = &jwk.alg {
+        return *alg == algorithm.to_string();
+    }
+    match (&jwk.key, algorithm) {
+        (JwkKey::Rsa { .. }, Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512) => true,
+        (JwkKey::Ec { crv, .. }, algorithm) => read_curve(crv).is_ok_and(|curve| EcCurve::for_algorithm(algorithm) == Some(curve)),
+        (JwkKey::Okp { .. }, Algorithm::EdDSA) => true,
+        (JwkKey::Oct { .. }, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt::Display;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::model::jwk::JwkSet;
+
+use super::key_set::DecodingKeySet;
+
+/// How long keys are cached when the response has no `Cache-Control` max-age
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// The shortest time between two fetches, however often keys are requested
+const DEFAULT_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Fetches a URL over HTTP, implemented by the caller with the HTTP client of their choice
+///
+/// It is also implemented for closures taking the URL, so a client can be plugged in without a wrapper type.
+///
+/// # Example
+/// ```rust,no_run
+/// use super_simple_jwt::{HttpFetch, HttpResponse};
+///
+/// struct StubFetch;
+///
+/// impl HttpFetch for StubFetch {
+///     fn get(&self, url: &str) -> Result<HttpResponse, String> {
+///         Ok(HttpResponse::new(200, br#"{"keys":[]}"#.to_vec()).with_cache_control("max-age=600"))
+///     }
+/// }
+/// ```
+pub trait HttpFetch: Send + Sync {
+    /// Sends a GET request to the URL
+    ///
+    /// # Arguments
+    /// * `url` - The URL to fetch
+    ///
+    /// # Returns
+    /// * `Result<HttpResponse, String>` - The response, whatever its status, or a message if no response was received
+    fn get(&self, url: &str) -> Result<HttpResponse, String>;
+}
+
+impl<F: Fn(&str) -> Result<HttpResponse, String> + Send + Sync> HttpFetch for F {
+    fn get(&self, url: &str) -> Result<HttpResponse, String> {
+        self(url)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The parts of an HTTP response a `JwksClient` uses
+///
+/// # Fields
+/// * `status` - The status code
+/// * `cache_control` - The value of the `Cache-Control` header, if present
+/// * `body` - The body of the response
+pub struct HttpResponse {
+    pub status: u16,
+    pub cache_control: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+
+    /// Creates a response without a `Cache-Control` header
+    ///
+    /// # Arguments
+    /// * `status` - The status code
+    /// * `body` - The body of the response
+    ///
+    /// # Returns
+    /// * A new `HttpResponse`
+    pub fn new(status: u16, body: Vec<u8>) -> Self {
+        HttpResponse { status, cache_control: None, body }
+    }
+
+    /// Sets the value of the `Cache-Control` header
+    pub fn with_cache_control(mut self, cache_control: impl Into<String>) -> Self {
+        self.cache_control = Some(cache_control.into());
+        self
+    }
+}
+
+/// Fetches and caches the JWK Set at a URL, e.g. the `jwks_uri` of an OpenID Connect provider
+///
+/// Keys are cached for the `max-age` of the `Cache-Control` header, or 5 minutes without one, and are not cached at all with `no-cache` or `no-store`.
+/// `verify_with_jwks_client` fetches the keys again when a token has a `kid` that is not in the set, so new keys are picked up straight away.
+/// However often keys are requested, the URL is fetched at most once per minimum refresh interval, 30 seconds by default.
+/// If the URL cannot be fetched, or any of its keys cannot be read, the last keys fetched are used until it can be again.
+/// The keys are read once when they are fetched, not for every token.
+///
+/// The URL is fetched by one request at a time, without holding up the others.
+/// While it is being fetched, other requests use the cached keys, and only wait for the fetch if no keys have been fetched yet.
+///
+/// # Example
+/// ```rust,no_run
+/// use super_simple_jwt::{verify_with_jwks_client, HttpResponse, JwksClient};
+/// # #[derive(serde::Serialize, serde::Deserialize, Clone)]
+/// # struct Claims { exp: usize, sub: String }
+///
+/// let client = JwksClient::new("https://example.com/.well-known/jwks.json", |url: &str| -> Result<HttpResponse, String> {
+///     // Send a GET request to the URL with the HTTP client of your choice
+///     Err(format!("No HTTP client to fetch {}", url))
+/// });
+///
+/// let claims: Claims = verify_with_jwks_client("pretend_this.is_a.valid_signed_token", &client).unwrap();
+/// ```
+pub struct JwksClient {
+    url: String,
+    fetcher: Box<dyn HttpFetch>,
+    default_max_age: Duration,
+    min_refresh_interval: Duration,
+    cache: Mutex<JwksCache>,
+    fetched: Condvar,
+}
+
+#[derive(Default)]
+struct JwksCache {
+    keys: Option<Arc<DecodingKeySet>>,
+    expires_at: Option<Instant>,
+    last_fetch: Option<Instant>,
+    last_error: Option<JwksError>,
+    fetching: bool,
+}
+
+/// Marks a fetch as no longer in flight if the `HttpFetch` panics, so requests waiting for it are not blocked forever
+struct FetchInFlight<'a>(&'a JwksClient);
+
+impl Drop for FetchInFlight<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.lock().fetching = false;
+            self.0.fetched.notify_all();
+        }
+    }
+}
+
+impl JwksClient {
+
+    /// Creates a client for the JWK Set at a URL, nothing is fetched until the keys are first needed
+    ///
+    /// # Arguments
+    /// * `url` - The URL of the JWK Set
+    /// * `fetcher` - The HTTP client used to fetch the URL
+    ///
+    /// # Returns
+    /// * A new `JwksClient`
+    pub fn new(url: impl Into<String>, fetcher: impl HttpFetch + 'static) -> Self {
+        JwksClient {
+            url: url.into(),
+            fetcher: Box::new(fetcher),
+            default_max_age: DEFAULT_MAX_AGE,
+            min_refresh_interval: DEFAULT_MIN_REFRESH_INTERVAL,
+            cache: Mutex::new(JwksCache::default()),
+            fetched: Condvar::new(),
+        }
+    }
+
+    /// Sets how long keys are cached when the response has no `Cache-Control` max-age
+    pub fn with_default_max_age(mut self, default_max_age: Duration) -> Self {
+        self.default_max_age = default_max_age;
+        self
+    }
+
+    /// Sets the shortest time between two fetches of the URL
+    pub fn with_min_refresh_interval(mut self, min_refresh_interval: Duration) -> Self {
+        self.min_refresh_interval = min_refresh_interval;
+        self
+    }
+
+    /// The keys, fetched if they are not cached or their max-age has passed
+    ///
+    /// # Returns
+    /// * `Result<Arc<DecodingKeySet>, JwksError>` - The keys, or the error of the last fetch if no keys were ever fetched
+    pub fn keys(&self) -> Result<Arc<DecodingKeySet>, JwksError> {
+        self.get(false)
+    }
+
+    /// Fetches the keys again, unless they were fetched within the minimum refresh interval
+    ///
+    /// # Returns
+    /// * `Result<Arc<DecodingKeySet>, JwksError>` - The keys, which are the last keys fetched if fetching fails, or the error if no keys were ever fetched
+    pub fn refresh(&self) -> Result<Arc<DecodingKeySet>, JwksError> {
+        self.get(true)
+    }
+
+    fn get(&self, force: bool) -> Result<Arc<DecodingKeySet>, JwksError> {
+        let mut cache = self.lock();
+
+        // Only requests with no keys to use wait for a fetch in flight
+        while cache.fetching && cache.keys.is_none() {
+            cache = self.fetched.wait(cache).unwrap_or_else(|err| err.into_inner());
+        }
+
+        let now = Instant::now();
+        let fresh = cache.expires_at.is_some_and(|expires_at| now < expires_at);
+        let rate_limited = cache.last_fetch.is_some_and(|last_fetch| now.duration_since(last_fetch) < self.min_refresh_interval);
+        if (force || !fresh) && !rate_limited && !cache.fetching {
+            cache.last_fetch = Some(now);
+            cache.fetching = true;
+            drop(cache);
+
+            // The lock is released while fetching, so a slow URL does not block requests that can use the cached keys
+            let in_flight = FetchInFlight(self);
+            let fetched = self.fetch();
+            drop(in_flight);
+
+            cache = self.lock();
+            cache.fetching = false;
+            match fetched {
+                Ok((keys, max_age)) => {
+                    cache.keys = Some(Arc::new(keys));
+                    cache.expires_at = Some(now + max_age);
+                    cache.last_error = None;
+                }
+                // The last keys are kept, and fetched again once the interval has passed
+                Err(err) => cache.last_error = Some(err),
+            }
+            self.fetched.notify_all();
+        }
+
+        match (&cache.keys, &cache.last_error) {
+            (Some(keys), _) => Ok(keys.clone()),
+            (None, Some(err)) => Err(err.clone()),
+            (None, None) => Err(JwksError::Fetch("The keys have not been fetched".to_string())),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, JwksCache> {
+        self.cache.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Fetches and reads the keys, returning them with how long they may be cached
+    fn fetch(&self) -> Result<(DecodingKeySet, Duration), JwksError> {
+        let response = self.fetcher.get(&self.url).map_err(JwksError::Fetch)?;
+        if !(200..300).contains(&response.status) {
+            return Err(JwksError::Status(response.status));
+        }
+
+        let jwks: JwkSet = serde_json::from_slice(&response.body).map_err(|err| JwksError::InvalidJwks(err.to_string()))?;
+        let keys = DecodingKeySet::from_jwk_set(&jwks).map_err(|err| JwksError::InvalidJwks(err.to_string()))?;
+        if keys.is_empty() {
+            return Err(JwksError::InvalidJwks("The JWK Set has no usable keys".to_string()));
+        }
+        let max_age = response.cache_control.as_deref().and_then(max_age).unwrap_or(self.default_max_age);
+        Ok((keys, max_age))
+    }
+}
+
+/// Reads how long a response may be cached from a `Cache-Control` header
+fn max_age(cache_control: &str) -> Option<Duration> {
+    let directives: Vec<_> = cache_control.split(',').map(|directive| directive.trim().to_ascii_lowercase()).collect();
+    if directives.iter().any(|directive| directive == "no-cache" || directive == "no-store") {
+        return Some(Duration::ZERO);
+    }
+    directives.iter()
+        .find_map(|directive| directive.strip_prefix("max-age="))
+        .and_then(|seconds| seconds.trim_matches('"').parse().ok())
+        .map(Duration::from_secs)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Error type returned when the keys of a `JwksClient` cannot be fetched
+///
+/// # Variants
+/// * `Fetch` - No response was received, with the message of the `HttpFetch`
+/// * `Status` - The response has a status other than 2xx
+/// * `InvalidJwks` - The response is not a JWK Set, has a key that cannot be read, or has no usable keys
+pub enum JwksError {
+    /// No response was received, with the message of the `HttpFetch`
+    Fetch(String),
+    /// The response has a status other than 2xx
+    Status(u16),
+    /// The response is not a JWK Set, has a key that cannot be read, or has no usable keys
+    InvalidJwks(String),
+}
+
+impl Display for JwksError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JwksError::Fetch(message) => write!(f, "Failed to fetch the JWK Set: {}", message),
+            JwksError::Status(status) => write!(f, "Fetching the JWK Set returned status {}", status),
+            JwksError::InvalidJwks(message) => write!(f, "Invalid JWK Set: {}", message),
+        }
+    }
+}
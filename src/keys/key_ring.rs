@@ -0,0 +1,184 @@
+use std::time::SystemTime;
+
+use crate::model::jwk::JwkSet;
+
+use super::decoding_key::{DecodingKey, DecodingKeyKind};
+use super::encoding_key::EncodingKey;
+
+/// The keys of a service during key rotation: one active key that signs tokens, and any number of older keys that still verify them
+///
+/// Every key has a key ID, which `sign_with_key_ring` puts in the `kid` of the tokens it signs and `verify_with_key_ring` uses to pick the key.
+/// Keys can have a not-before and not-after time, outside of which they are not used.
+///
+/// # Example
+/// ```rust
+/// use std::time::{Duration, SystemTime};
+/// use super_simple_jwt::{Algorithm, EncodingKey, KeyRing};
+///
+/// let mut key_ring = KeyRing::new("2025-01", EncodingKey::generate(&Algorithm::ES256).unwrap());
+///
+/// // Sign with a new key, while tokens signed with the old key still verify for a day
+/// let retire_after = SystemTime::now() + Duration::from_secs(24 * 60 * 60);
+/// key_ring.rotate("2025-02", EncodingKey::generate(&Algorithm::ES256).unwrap(), Some(retire_after));
+/// ```
+#[derive(Clone)]
+pub struct KeyRing {
+    signing_key: EncodingKey,
+    entries: Vec<KeyRingEntry>,
+}
+
+/// A key in a `KeyRing` that verifies tokens, with its key ID and the times it may be used between
+///
+/// # Fields
+/// * `kid` - The key ID
+/// * `key` - The key that verifies tokens with this key ID
+/// * `not_before` - The key is not used before this time, if set
+/// * `not_after` - The key is not used after this time, if set
+#[derive(Clone)]
+pub struct KeyRingEntry {
+    pub kid: String,
+    pub key: DecodingKey,
+    pub not_before: Option<SystemTime>,
+    pub not_after: Option<SystemTime>,
+}
+
+impl KeyRingEntry {
+
+    /// Creates an entry that can always be used
+    ///
+    /// # Arguments
+    /// * `kid` - The key ID
+    /// * `key` - The key that verifies tokens with this key ID
+    ///
+    /// # Returns
+    /// * A new `KeyRingEntry`
+    pub fn new(kid: impl Into<String>, key: DecodingKey) -> Self {
+        KeyRingEntry {
+            kid: kid.into(),
+            key,
+            not_before: None,
+            not_after: None,
+        }
+    }
+
+    /// Sets the time before which the key is not used
+    pub fn with_not_before(mut self, not_before: SystemTime) -> Self {
+        self.not_before = Some(not_before);
+        self
+    }
+
+    /// Sets the time after which the key is not used
+    pub fn with_not_after(mut self, not_after: SystemTime) -> Self {
+        self.not_after = Some(not_after);
+        self
+    }
+
+    /// Whether the key may be used at a time
+    pub fn is_valid_at(&self, time: SystemTime) -> bool {
+        self.not_before.is_none_or(|not_before| time >= not_before) && self.not_after.is_none_or(|not_after| time <= not_after)
+    }
+}
+
+impl KeyRing {
+
+    /// Creates a key ring with an active signing key, which also verifies the tokens it signs
+    ///
+    /// # Arguments
+    /// * `kid` - The key ID of the signing key
+    /// * `signing_key` - The key that signs tokens
+    ///
+    /// # Returns
+    /// * A new `KeyRing`
+    pub fn new(kid: impl Into<String>, signing_key: EncodingKey) -> Self {
+        let kid = kid.into();
+        let entry = KeyRingEntry::new(kid.clone(), signing_key.to_decoding_key());
+        KeyRing {
+            signing_key: signing_key.with_kid(kid),
+            entries: vec![entry],
+        }
+    }
+
+    /// Adds a key that verifies tokens, replacing any key with the same key ID
+    ///
+    /// Use this to publish a key before it becomes the active key, or to keep verifying tokens from another signer.
+    ///
+    /// # Arguments
+    /// * `entry` - The key and its key ID
+    pub fn add(&mut self, entry: KeyRingEntry) -> &mut Self {
+        self.entries.retain(|existing| existing.kid != entry.kid);
+        self.entries.push(entry);
+        self
+    }
+
+    /// Makes a new key the active signing key, keeping the previous one to verify tokens
+    ///
+    /// # Arguments
+    /// * `kid` - The key ID of the new signing key
+    /// * `signing_key` - The new key that signs tokens
+    /// * `retire_after` - The time after which the previous key no longer verifies tokens, or `None` to keep it until it is removed
+    pub fn rotate(&mut self, kid: impl Into<String>, signing_key: EncodingKey, retire_after: Option<SystemTime>) -> &mut Self {
+        let previous = self.active_kid().to_string();
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.kid == previous) {
+            entry.not_after = retire_after.or(entry.not_after);
+        }
+
+        let kid = kid.into();
+        self.add(KeyRingEntry::new(kid.clone(), signing_key.to_decoding_key()));
+        self.signing_key = signing_key.with_kid(kid);
+        self
+    }
+
+    /// Removes a key that only verifies tokens, the active key cannot be removed
+    ///
+    /// # Arguments
+    /// * `kid` - The key ID of the key to remove
+    ///
+    /// # Returns
+    /// * `Option<KeyRingEntry>` - The removed key, or `None` if there is no such key or it is the active key
+    pub fn remove(&mut self, kid: &str) -> Option<KeyRingEntry> {
+        if kid == self.active_kid() {
+            return None;
+        }
+        let index = self.entries.iter().position(|entry| entry.kid == kid)?;
+        Some(self.entries.remove(index))
+    }
+
+    /// The key ID of the active signing key
+    pub fn active_kid(&self) -> &str {
+        self.signing_key.kid().unwrap_or_default()
+    }
+
+    /// The key with a key ID, whether or not it is currently valid
+    pub fn get(&self, kid: &str) -> Option<&KeyRingEntry> {
+        self.entries.iter().find(|entry| entry.kid == kid)
+    }
+
+    /// The keys that verify tokens, including the active key
+    pub fn entries(&self) -> &[KeyRingEntry] {
+        &self.entries
+    }
+
+    /// The public keys that currently verify tokens as a JWK Set, to publish for other verifiers
+    ///
+    /// Shared secrets and keys outside their validity window are left out.
+    ///
+    /// # Returns
+    /// * `JwkSet` - The public keys, each with its key ID
+    pub fn to_jwk_set(&self) -> JwkSet {
+        let now = SystemTime::now();
+        let keys = self.entries.iter()
+            .filter(|entry| entry.is_valid_at(now) && !matches!(entry.key.kind.as_ref(), DecodingKeyKind::Hmac(_)))
+            .map(|entry| {
+                let mut jwk = entry.key.to_jwk();
+                jwk.kid = Some(entry.kid.clone());
+                jwk
+            })
+            .collect();
+        JwkSet::new(keys)
+    }
+
+    /// The active signing key, with its key ID set
+    pub(crate) fn signing_key(&self) -> &EncodingKey {
+        &self.signing_key
+    }
+}
@@ -0,0 +1,91 @@
+use crate::model::jwk::{Jwk, JwkSet};
+
+use super::decoding_key::DecodingKey;
+use super::KeyError;
+
+/// The keys of a JWK Set, each read into a `DecodingKey` once so tokens are verified without parsing the keys again
+///
+/// Use it to verify tokens with `verify_with_jwks`, which picks the key by the `kid` of the token.
+/// `KeySource` and `JwksClient` keep their keys as a `DecodingKeySet`, so their keys are checked when they are loaded.
+///
+/// # Example
+/// ```rust,no_run
+/// use super_simple_jwt::{verify_with_jwks, DecodingKeySet, JwkSet};
+/// # #[derive(serde::Serialize, serde::Deserialize, Clone)]
+/// # struct Claims { exp: usize, sub: String }
+///
+/// let json = std::fs::read_to_string("jwks.json").unwrap();
+/// let jwks: JwkSet = serde_json::from_str(&json).unwrap();
+/// let keys = DecodingKeySet::from_jwk_set(&jwks).unwrap();
+///
+/// let claims: Claims = verify_with_jwks("pretend_this.is_a.valid_signed_token", &keys).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct DecodingKeySet {
+    entries: Vec<KeySetEntry>,
+}
+
+/// A key of a `DecodingKeySet`, with the JWK it was read from to pick it by key ID and algorithm
+#[derive(Clone)]
+pub(crate) struct KeySetEntry {
+    pub(crate) jwk: Jwk,
+    /// `None` for keys whose `use` or `key_ops` do not allow verifying, which are kept so their key ID is still known
+    pub(crate) key: Option<DecodingKey>,
+}
+
+impl DecodingKeySet {
+
+    /// Reads every key of a JWK Set
+    ///
+    /// Keys whose `use` or `key_ops` do not allow verifying are not read, and are never used to verify tokens.
+    ///
+    /// # Arguments
+    /// * `jwks` - The JWK Set
+    ///
+    /// # Returns
+    /// * `Result<DecodingKeySet, KeyError>` - The keys, or the error of the first key that could not be read
+    pub fn from_jwk_set(jwks: &JwkSet) -> Result<Self, KeyError> {
+        let mut entries = Vec::with_capacity(jwks.keys.len());
+        for jwk in &jwks.keys {
+            let key = if jwk.allows("verify") { Some(DecodingKey::from_jwk(jwk)?) } else { None };
+            entries.push(KeySetEntry { jwk: jwk.clone(), key });
+        }
+        Ok(DecodingKeySet { entries })
+    }
+
+    /// Creates a set from keys that have already been read, with the JWKs to pick them by
+    pub(crate) fn from_keys(keys: Vec<(Jwk, DecodingKey)>) -> Self {
+        let entries = keys.into_iter().map(|(jwk, key)| KeySetEntry { jwk, key: Some(key) }).collect();
+        DecodingKeySet { entries }
+    }
+
+    /// Finds the JWKs with a key ID
+    ///
+    /// # Arguments
+    /// * `kid` - The key ID
+    ///
+    /// # Returns
+    /// * An iterator over the JWKs with the key ID, usually at most one
+    pub fn find<'a>(&'a self, kid: &'a str) -> impl Iterator<Item = &'a Jwk> + 'a {
+        self.entries.iter().map(|entry| &entry.jwk).filter(move |jwk| jwk.kid.as_deref() == Some(kid))
+    }
+
+    /// The JWKs the keys were read from
+    pub fn jwks(&self) -> impl Iterator<Item = &Jwk> {
+        self.entries.iter().map(|entry| &entry.jwk)
+    }
+
+    /// The number of keys in the set
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the set has no keys
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn entries(&self) -> &[KeySetEntry] {
+        &self.entries
+    }
+}
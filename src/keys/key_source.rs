@@ -0,0 +1,228 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+use crate::model::jwk::JwkSet;
+
+use super::decoding_key::DecodingKey;
+use super::key_set::DecodingKeySet;
+use super::KeyError;
+
+/// A set of verifying keys read from local files, which can be reloaded when the files change
+///
+/// The keys are read from a JWKS file, or from a directory of PEM public keys and certificates, e.g. a mounted Kubernetes secret.
+/// A reload swaps in the new set atomically, so verifiers always see either the old or the new keys, never a mix.
+/// Every key is read when the files are loaded, and if the files or any of their keys cannot be read, the last good set is kept.
+///
+/// Clones share the same keys, so a clone can be handed to each verifier.
+///
+/// # Example
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use super_simple_jwt::{verify_with_jwks, KeySource};
+/// # #[derive(serde::Serialize, serde::Deserialize, Clone)]
+/// # struct Claims { exp: usize, sub: String }
+///
+/// let key_source = KeySource::from_jwks_file("/etc/keys/jwks.json").unwrap();
+/// let _watcher = key_source.watch(Duration::from_secs(10), |err| eprintln!("Keeping the previous keys: {}", err));
+///
+/// let claims: Claims = verify_with_jwks("pretend_this.is_a.valid_signed_token", &key_source.keys()).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct KeySource {
+    inner: Arc<KeySourceInner>,
+}
+
+struct KeySourceInner {
+    location: KeyLocation,
+    keys: RwLock<Arc<DecodingKeySet>>,
+    /// The digest of the files last read, whether or not they could be parsed
+    fingerprint: Mutex<Vec<u8>>,
+}
+
+/// Where the keys of a `KeySource` are read from
+enum KeyLocation {
+    JwksFile(PathBuf),
+    PemDirectory(PathBuf),
+}
+
+impl KeySource {
+
+    /// Reads the keys from a JWK Set file
+    ///
+    /// # Arguments
+    /// * `path` - The path of the JWKS file
+    ///
+    /// # Returns
+    /// * `Result<KeySource, KeyError>` - The key source, or an error if the file could not be read or has no usable keys
+    pub fn from_jwks_file(path: impl AsRef<Path>) -> Result<Self, KeyError> {
+        Self::load(KeyLocation::JwksFile(path.as_ref().to_path_buf()))
+    }
+
+    /// Reads the keys from the `.pem` files in a directory, each holding a public key or certificate
+    ///
+    /// The key ID of each key is the name of its file without the extension, e.g. `2025-01` for `2025-01.pem`.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the directory
+    ///
+    /// # Returns
+    /// * `Result<KeySource, KeyError>` - The key source, or an error if a file could not be read or there are no keys
+    pub fn from_pem_directory(path: impl AsRef<Path>) -> Result<Self, KeyError> {
+        Self::load(KeyLocation::PemDirectory(path.as_ref().to_path_buf()))
+    }
+
+    /// The current keys, which stay the same for the caller even if the source is reloaded
+    pub fn keys(&self) -> Arc<DecodingKeySet> {
+        self.inner.keys.read().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+
+    /// Reads the files again and swaps in the new keys if they changed
+    ///
+    /// Files that have not changed since the last reload are not parsed again, so an error is only returned once for each change.
+    ///
+    /// # Returns
+    /// * `Result<bool, KeyError>` - Whether the keys were replaced, or an error if the files could not be read, in which case the last good keys are kept
+    pub fn reload(&self) -> Result<bool, KeyError> {
+        let files = self.inner.location.read_files()?;
+        let fingerprint = fingerprint(&files);
+
+        let mut last_fingerprint = self.inner.fingerprint.lock().unwrap_or_else(|err| err.into_inner());
+        if *last_fingerprint == fingerprint {
+            return Ok(false);
+        }
+        *last_fingerprint = fingerprint;
+
+        let keys = self.inner.location.parse(&files)?;
+        *self.inner.keys.write().unwrap_or_else(|err| err.into_inner()) = Arc::new(keys);
+        Ok(true)
+    }
+
+    /// Reloads the keys on a background thread at an interval, until the returned `KeyWatcher` is dropped
+    ///
+    /// # Arguments
+    /// * `interval` - How often the files are checked for changes
+    /// * `on_error` - Called with the error when changed files cannot be read, the last good keys are kept
+    ///
+    /// # Returns
+    /// * `KeyWatcher` - The handle of the background thread, which stops it when dropped
+    pub fn watch(&self, interval: Duration, on_error: impl Fn(KeyError) + Send + 'static) -> KeyWatcher {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let key_source = self.clone();
+        let thread = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                if let Err(err) = key_source.reload() {
+                    on_error(err);
+                }
+            }
+        });
+        KeyWatcher { stop: Some(stop), thread: Some(thread) }
+    }
+
+    fn load(location: KeyLocation) -> Result<Self, KeyError> {
+        let files = location.read_files()?;
+        let keys = location.parse(&files)?;
+        Ok(KeySource {
+            inner: Arc::new(KeySourceInner {
+                location,
+                keys: RwLock::new(Arc::new(keys)),
+                fingerprint: Mutex::new(fingerprint(&files)),
+            }),
+        })
+    }
+}
+
+/// The handle of the background thread started by `KeySource::watch`, which stops the thread when dropped
+pub struct KeyWatcher {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for KeyWatcher {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the thread, which then stops
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl KeyLocation {
+
+    /// Reads the names and contents of the files the keys are in
+    fn read_files(&self) -> Result<Vec<(String, Vec<u8>)>, KeyError> {
+        match self {
+            KeyLocation::JwksFile(path) => Ok(vec![(path.display().to_string(), read_file(path)?)]),
+            KeyLocation::PemDirectory(path) => {
+                let entries = std::fs::read_dir(path).map_err(|err| KeyError::InvalidKey(format!("Failed to read {}: {}", path.display(), err)))?;
+                let mut files = Vec::new();
+                for entry in entries {
+                    let path = entry.map_err(|err| KeyError::InvalidKey(format!("Failed to read {}: {}", path.display(), err)))?.path();
+                    // Symbolic links are followed, as mounted secrets are links to the current version
+                    if path.extension().is_some_and(|extension| extension == "pem") && path.is_file() {
+                        files.push((path.file_stem().unwrap_or_default().to_string_lossy().to_string(), read_file(&path)?));
+                    }
+                }
+                files.sort();
+                Ok(files)
+            }
+        }
+    }
+
+    /// Parses the files into a key set
+    fn parse(&self, files: &[(String, Vec<u8>)]) -> Result<DecodingKeySet, KeyError> {
+        let keys = match self {
+            KeyLocation::JwksFile(path) => {
+                let (_, contents) = &files[0];
+                let jwks = serde_json::from_slice::<JwkSet>(contents).map_err(|err| KeyError::InvalidKey(format!("Failed to read JWKS {}: {}", path.display(), err)))?;
+                DecodingKeySet::from_jwk_set(&jwks).map_err(|err| KeyError::InvalidKey(format!("Failed to read a key of JWKS {}: {}", path.display(), err)))?
+            }
+            KeyLocation::PemDirectory(_) => {
+                let mut keys = Vec::new();
+                for (kid, contents) in files {
+                    let pem = String::from_utf8_lossy(contents);
+                    let key = DecodingKey::from_public_pem(&pem).map_err(|err| KeyError::InvalidKey(format!("Failed to read {}.pem: {}", kid, err)))?;
+                    let mut jwk = key.to_jwk();
+                    jwk.kid = Some(kid.clone());
+                    keys.push((jwk, key));
+                }
+                DecodingKeySet::from_keys(keys)
+            }
+        };
+
+        // A half-written or emptied mount should not remove every key
+        if keys.is_empty() {
+            return Err(KeyError::InvalidKey(format!("{} has no usable keys", self)));
+        }
+        Ok(keys)
+    }
+}
+
+impl std::fmt::Display for KeyLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyLocation::JwksFile(path) | KeyLocation::PemDirectory(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, KeyError> {
+    std::fs::read(path).map_err(|err| KeyError::InvalidKey(format!("Failed to read {}: {}", path.display(), err)))
+}
+
+/// A digest of the names and contents of the files, to tell whether they changed
+fn fingerprint(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut digest = Sha256::new();
+    for (name, contents) in files {
+        digest.update((name.len() as u64).to_be_bytes());
+        digest.update(name);
+        digest.update((contents.len() as u64).to_be_bytes());
+        digest.update(contents);
+    }
+    digest.finalize().to_vec()
+}
@@ -0,0 +1,221 @@
+use std::fmt::Display;
+
+use pkcs8::ObjectIdentifier;
+use pkcs8::AssociatedOid;
+use pkcs8::der::{Decode, SecretDocument};
+use pkcs8::spki::SubjectPublicKeyInfoRef;
+use pkcs8::{EncryptedPrivateKeyInfo, PrivateKeyInfo};
+use x509_cert::Certificate;
+
+use crate::model::header::Algorithm;
+
+pub(crate) mod certificate;
+pub mod decoding_key;
+pub mod encoding_key;
+pub(crate) mod jwk;
+pub mod jwks_client;
+pub mod key_ring;
+pub mod key_set;
+pub mod key_source;
+pub mod policy;
+
+/// The object identifier of RSA keys, from RFC 8017
+pub(crate) const RSA_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+
+/// The object identifier of EC keys, from RFC 5480
+pub(crate) const EC_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+
+/// The object identifier of Ed25519 keys, from RFC 8410
+pub(crate) const ED25519_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+/// The object identifier of Ed448 keys, from RFC 8410
+pub(crate) const ED448_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.113");
+
+/// The name of the type of key with an algorithm object identifier, used in error messages
+pub(crate) fn key_type_name(oid: ObjectIdentifier) -> String {
+    match oid {
+        RSA_OID => "RSA".to_string(),
+        EC_OID => "EC".to_string(),
+        ED25519_OID => "Ed25519".to_string(),
+        ED448_OID => "Ed448".to_string(),
+        oid => format!("unsupported ({})", oid),
+    }
+}
+
+/// The formats keys are read from, found using the label of a PEM block or the structure of DER
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum KeyFormat {
+    /// `RSA PRIVATE KEY`
+    Pkcs1Private,
+    /// `RSA PUBLIC KEY`
+    Pkcs1Public,
+    /// `EC PRIVATE KEY`
+    Sec1,
+    /// `PRIVATE KEY`
+    Pkcs8,
+    /// `ENCRYPTED PRIVATE KEY`
+    EncryptedPkcs8,
+    /// `PUBLIC KEY`
+    Spki,
+    /// `CERTIFICATE`
+    Certificate,
+}
+
+impl KeyFormat {
+    /// Reads a PEM block, returning its format and DER contents
+    pub(crate) fn read(pem: &str) -> Result<(KeyFormat, SecretDocument), KeyError> {
+        let (label, document) = SecretDocument::from_pem(pem).map_err(|err| KeyError::InvalidKey(err.to_string()))?;
+        let format = match label {
+            "RSA PRIVATE KEY" => KeyFormat::Pkcs1Private,
+            "RSA PUBLIC KEY" => KeyFormat::Pkcs1Public,
+            "EC PRIVATE KEY" => KeyFormat::Sec1,
+            "PRIVATE KEY" => KeyFormat::Pkcs8,
+            "ENCRYPTED PRIVATE KEY" => KeyFormat::EncryptedPkcs8,
+            "PUBLIC KEY" => KeyFormat::Spki,
+            "CERTIFICATE" => KeyFormat::Certificate,
+            label => return Err(KeyError::WrongFormat(format!("Unsupported PEM block '{}'", label))),
+        };
+        Ok((format, document))
+    }
+
+    /// Detects the format of DER, which unlike PEM has no label
+    ///
+    /// The structures are distinct enough that at most one of them parses.
+    pub(crate) fn detect(der: &[u8]) -> Result<KeyFormat, KeyError> {
+        if Certificate::from_der(der).is_ok() {
+            Ok(KeyFormat::Certificate)
+        } else if PrivateKeyInfo::try_from(der).is_ok() {
+            Ok(KeyFormat::Pkcs8)
+        } else if EncryptedPrivateKeyInfo::try_from(der).is_ok() {
+            Ok(KeyFormat::EncryptedPkcs8)
+        } else if SubjectPublicKeyInfoRef::try_from(der).is_ok() {
+            Ok(KeyFormat::Spki)
+        } else if sec1::EcPrivateKey::from_der(der).is_ok() {
+            Ok(KeyFormat::Sec1)
+        } else if rsa::pkcs1::RsaPrivateKey::from_der(der).is_ok() {
+            Ok(KeyFormat::Pkcs1Private)
+        } else if rsa::pkcs1::RsaPublicKey::from_der(der).is_ok() {
+            Ok(KeyFormat::Pkcs1Public)
+        } else {
+            Err(KeyError::InvalidKey("DER is not a PKCS#1, PKCS#8, encrypted PKCS#8, SEC1 or SPKI key, or an X.509 certificate".to_string()))
+        }
+    }
+}
+
+impl Display for KeyFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyFormat::Pkcs1Private => write!(f, "a PKCS#1 RSA private key (RSA PRIVATE KEY)"),
+            KeyFormat::Pkcs1Public => write!(f, "a PKCS#1 RSA public key (RSA PUBLIC KEY)"),
+            KeyFormat::Sec1 => write!(f, "a SEC1 EC private key (EC PRIVATE KEY)"),
+            KeyFormat::Pkcs8 => write!(f, "a PKCS#8 private key (PRIVATE KEY)"),
+            KeyFormat::EncryptedPkcs8 => write!(f, "an encrypted PKCS#8 private key (ENCRYPTED PRIVATE KEY)"),
+            KeyFormat::Spki => write!(f, "an SPKI public key (PUBLIC KEY)"),
+            KeyFormat::Certificate => write!(f, "an X.509 certificate (CERTIFICATE)"),
+        }
+    }
+}
+
+/// Whether a key given as a string is a PEM or JWK document rather than a shared secret
+///
+/// Such a key is often public, so using it as an HMAC secret would let anyone holding it forge tokens.
+pub(crate) fn is_key_document(key: &str) -> bool {
+    let key = key.trim_start();
+    key.starts_with("-----BEGIN") || key.starts_with('{')
+}
+
+/// The error returned when a key is read with the wrong constructor, naming what was expected and what was found
+pub(crate) fn wrong_format(expected: &str, found: impl Display) -> KeyError {
+    KeyError::WrongFormat(format!("Expected {} but found {}", expected, found))
+}
+
+/// The elliptic curves supported for ECDSA
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EcCurve {
+    P256,
+    P384,
+    P521,
+    Secp256k1,
+}
+
+impl EcCurve {
+    /// The curve required by an ECDSA algorithm
+    pub(crate) fn for_algorithm(algorithm: &Algorithm) -> Option<EcCurve> {
+        match algorithm {
+            Algorithm::ES256 => Some(EcCurve::P256),
+            Algorithm::ES384 => Some(EcCurve::P384),
+            Algorithm::ES512 => Some(EcCurve::P521),
+            Algorithm::ES256K => Some(EcCurve::Secp256k1),
+            _ => None,
+        }
+    }
+
+    /// The curve named by an object identifier
+    pub(crate) fn from_oid(oid: ObjectIdentifier) -> Option<EcCurve> {
+        match oid {
+            p256::NistP256::OID => Some(EcCurve::P256),
+            p384::NistP384::OID => Some(EcCurve::P384),
+            p521::NistP521::OID => Some(EcCurve::P521),
+            k256::Secp256k1::OID => Some(EcCurve::Secp256k1),
+            _ => None,
+        }
+    }
+}
+
+impl Display for EcCurve {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EcCurve::P256 => write!(f, "P-256"),
+            EcCurve::P384 => write!(f, "P-384"),
+            EcCurve::P521 => write!(f, "P-521"),
+            EcCurve::Secp256k1 => write!(f, "secp256k1"),
+        }
+    }
+}
+
+/// Returns an error if a key for `curve` is used with an algorithm that requires a different curve
+pub(crate) fn check_curve(curve: EcCurve, algorithm: &Algorithm) -> Result<(), KeyError> {
+    match EcCurve::for_algorithm(algorithm) {
+        Some(required) if required == curve => Ok(()),
+        Some(required) => Err(KeyError::IncompatibleKey(format!("Key is for curve {} but {} requires curve {}", curve, algorithm, required))),
+        None => Err(KeyError::IncompatibleKey(format!("An EC key cannot be used with {}", algorithm))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Error type returned when reading a key fails, or the key cannot be used for an algorithm
+///
+/// # Variants
+/// * `InvalidKey` - The key could not be read
+/// * `WrongFormat` - The key is in a format or of a type the constructor does not read, e.g. a public key given where a private key is expected
+/// * `WrongPassphrase` - The passphrase for an encrypted key is wrong
+/// * `InvalidCertificate` - The certificate is outside its validity window, or its key usage forbids digital signatures
+/// * `IncompatibleKey` - The key is valid but cannot be used with the algorithm
+/// * `WeakKey` - The key is weaker than the `KeyPolicy` it was read with, e.g. an RSA modulus under 2048 bits
+pub enum KeyError {
+    /// The key could not be read
+    InvalidKey(String),
+    /// The key is in a format or of a type the constructor does not read, the message names the format that was found
+    WrongFormat(String),
+    /// The passphrase for an encrypted key is wrong
+    WrongPassphrase,
+    /// The certificate is outside its validity window, or its key usage forbids digital signatures
+    InvalidCertificate(String),
+    /// The key is valid but cannot be used with the algorithm
+    IncompatibleKey(String),
+    /// The key is weaker than the `KeyPolicy` it was read with, e.g. an RSA modulus under 2048 bits
+    WeakKey(String),
+}
+
+impl Display for KeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyError::InvalidKey(message) => write!(f, "{}", message),
+            KeyError::WrongFormat(message) => write!(f, "{}", message),
+            KeyError::WrongPassphrase => write!(f, "The passphrase for the encrypted key is wrong"),
+            KeyError::InvalidCertificate(message) => write!(f, "{}", message),
+            KeyError::IncompatibleKey(message) => write!(f, "{}", message),
+            KeyError::WeakKey(message) => write!(f, "{}", message),
+        }
+    }
+}
\ No newline at end of file
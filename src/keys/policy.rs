@@ -0,0 +1,111 @@
+use rsa::traits::PublicKeyParts;
+use rsa::BigUint;
+
+use crate::model::header::Algorithm;
+
+use super::KeyError;
+
+/// The length of the shortest HMAC secret read with `hmac_hash_length`, the output of SHA-256 used by `HS256`
+const MIN_HMAC_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The minimum strength of the keys that are accepted
+///
+/// RSA keys and the length of HMAC secrets are checked when a key is read, HMAC secrets must be at least 32 bytes.
+/// The length an HMAC algorithm needs, e.g. 48 bytes for `HS384`, is checked when the key is read if the algorithm is known then,
+/// e.g. from the `alg` of a JWK, and otherwise when the secret is used with it.
+///
+/// Every key constructor applies `KeyPolicy::default()`. Keys that do not meet it can still be read with the
+/// `_with_policy` constructors, e.g. `DecodingKey::from_rsa_pem_with_policy` with `KeyPolicy::legacy()`.
+///
+/// # Fields
+/// * `min_rsa_bits` - The smallest RSA modulus accepted, in bits
+/// * `rsa_exponents` - The RSA public exponents accepted, or `None` to accept any the key parses with
+/// * `hmac_hash_length` - Whether HMAC secrets must be at least as long as the output of the hash function, e.g. 32 bytes for `HS256`, as RFC 7518 requires.
+///   Secrets shorter than 32 bytes, the output of the shortest hash, are rejected when they are read
+///
+/// # Example
+/// ```rust,no_run
+/// use super_simple_jwt::{DecodingKey, KeyPolicy};
+///
+/// let pem = std::fs::read_to_string("legacy_1024_bit_public.pem").unwrap();
+/// let key = DecodingKey::from_rsa_pem_with_policy(&pem, &KeyPolicy::legacy()).unwrap();
+/// ```
+pub struct KeyPolicy {
+    pub min_rsa_bits: usize,
+    pub rsa_exponents: Option<Vec<u64>>,
+    pub hmac_hash_length: bool,
+}
+
+impl Default for KeyPolicy {
+    /// RSA moduli of at least 2048 bits with the public exponent 65537, and HMAC secrets as long as the hash output
+    fn default() -> Self {
+        KeyPolicy {
+            min_rsa_bits: 2048,
+            rsa_exponents: Some(vec![65537]),
+            hmac_hash_length: true,
+        }
+    }
+}
+
+impl KeyPolicy {
+
+    /// A policy for keys issued before stronger keys were required: RSA moduli of at least 1024 bits with any exponent, and HMAC secrets of any length
+    ///
+    /// Only use it for keys that cannot be replaced yet.
+    pub fn legacy() -> Self {
+        KeyPolicy {
+            min_rsa_bits: 1024,
+            rsa_exponents: None,
+            hmac_hash_length: false,
+        }
+    }
+
+    /// Checks the modulus size and public exponent of an RSA key
+    pub(crate) fn check_rsa(&self, key: &impl PublicKeyParts) -> Result<(), KeyError> {
+        let bits = key.n().bits();
+        if bits < self.min_rsa_bits {
+            return Err(KeyError::WeakKey(format!("The RSA modulus is {} bits, at least {} bits are required", bits, self.min_rsa_bits)));
+        }
+        if let Some(exponents) = &self.rsa_exponents
+            && !exponents.iter().any(|exponent| BigUint::from(*exponent) == *key.e()) {
+            return Err(KeyError::WeakKey(format!("The RSA public exponent {} is not accepted, it must be one of {:?}", key.e(), exponents)));
+        }
+        Ok(())
+    }
+
+    /// Checks an HMAC secret is long enough for its algorithm when that is known as the key is read, e.g. from the `alg` of a JWK,
+    /// or for the shortest HMAC algorithm when it is not, in which case the length the algorithm needs is checked when it is used
+    pub(crate) fn check_hmac(&self, secret: &[u8], algorithm: Option<&Algorithm>) -> Result<(), KeyError> {
+        if !self.hmac_hash_length {
+            return Ok(());
+        }
+        match algorithm {
+            Some(algorithm) => check_hmac_length(secret, algorithm).map_err(KeyError::WeakKey),
+            None if secret.len() < MIN_HMAC_LENGTH => Err(KeyError::WeakKey(format!("The HMAC secret is {} bytes, at least {} bytes are required", secret.len(), MIN_HMAC_LENGTH))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Checks that an HMAC secret is at least as long as the output of the hash function of the algorithm
+pub(crate) fn check_hmac_length(secret: &[u8], algorithm: &Algorithm) -> Result<(), String> {
+    // Other algorithms are rejected by the caller
+    if !matches!(algorithm, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+        return Ok(());
+    }
+    let required = hmac_length(algorithm);
+    if secret.len() < required {
+        return Err(format!("{} requires a secret of at least {} bytes, the length of the hash output, but the secret is {} bytes", algorithm, required, secret.len()));
+    }
+    Ok(())
+}
+
+/// The output length of the hash function of an HMAC algorithm, which is also the recommended secret length
+pub(crate) fn hmac_length(algorithm: &Algorithm) -> usize {
+    match algorithm {
+        Algorithm::HS384 => 48,
+        Algorithm::HS512 => 64,
+        _ => 32,
+    }
+}
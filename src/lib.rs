@@ -1,12 +1,24 @@
 mod decoding;
 mod encoding;
+mod keys;
 mod model;
 mod signing;
 mod tests;
+#[cfg(feature = "dangerous-unsecured")]
+mod unsecured;
 mod verifying;
 
-pub use crate::signing::{sign, SigningError as SignTokenError};
-pub use crate::verifying::{verify, VerifyingTokenError as VerifyTokenError};
+pub use crate::signing::{sign, sign_async, sign_with_key, sign_with_key_ring, signer::{AsyncSigner, Signer}, SigningError as SignTokenError};
+pub use crate::verifying::{verifier::{AsyncVerifier, Verifier}, verify, verify_async, verify_legacy, verify_with_jwks, verify_with_jwks_client, verify_with_key, verify_with_key_ring, verify_with_x5c, VerifyingTokenError as VerifyTokenError};
 pub use crate::decoding::{claims::{decode as decode_claims, ClaimsDecodeError as DecodeClaimsError}, header::{decode as decode_header, HeaderDecodeError as DecodeHeaderError}};
 pub use crate::encoding::{claims::{encode as encode_claims, ClaimsEncodeError as EncodeClaimsError}, header::{encode as encode_header, HeaderEncodeError as EncodeHeaderError}};
-pub use crate::model::header::{ Algorithm, Header };
\ No newline at end of file
+pub use crate::model::header::{ Algorithm, Header };
+pub use crate::model::jwk::{Jwk, JwkKey, JwkSet};
+pub use crate::keys::{certificate::{CertificateChainError, TrustAnchors}, decoding_key::DecodingKey, encoding_key::EncodingKey, jwks_client::{HttpFetch, HttpResponse, JwksClient, JwksError}, key_ring::{KeyRing, KeyRingEntry}, key_set::DecodingKeySet, key_source::{KeySource, KeyWatcher}, policy::KeyPolicy, KeyError};
+pub use zeroize::Zeroizing;
+#[cfg(any(test, feature = "test-util"))]
+pub use crate::signing::mock::MockSigner;
+#[cfg(feature = "pkcs11")]
+pub use crate::signing::pkcs11::{Pkcs11Error, Pkcs11Key, Pkcs11Module, Pkcs11Signer};
+#[cfg(feature = "dangerous-unsecured")]
+pub use crate::unsecured::{encode_unsecured as dangerous_encode_unsecured, decode_unsecured as dangerous_decode_unsecured, UnsecuredTokenError};
\ No newline at end of file
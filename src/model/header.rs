@@ -1,17 +1,74 @@
 use std::{fmt::Display, str::FromStr};
 
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 use crate::decoding::header::HeaderDecodeError;
+use crate::model::jwk::Jwk;
+
+/// The names of the header parameters registered by RFC 7515, which cannot be used for extra parameters
+pub(crate) const REGISTERED_PARAMETERS: [&str; 11] = ["alg", "typ", "cty", "kid", "jku", "jwk", "x5u", "x5c", "x5t", "x5t#S256", "crit"];
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// The header of a JWT, with the registered parameters of RFC 7515
+///
+/// Every parameter other than `alg` is optional and only written to the token when set.
+///
+/// # Fields
+/// * `alg` - The algorithm used to sign the token
+/// * `typ` - The media type of the token, `JWT` unless set otherwise, e.g. `at+jwt` for RFC 9068 access tokens
+/// * `cty` - The media type of the payload, `JWT` for a nested token
+/// * `kid` - The ID of the key used to sign the token, used to pick the key to verify it with
+/// * `jku` - A URL of a JWK Set holding the signing key, never fetched by this crate
+/// * `jwk` - The public key used to sign the token, which is not trusted by this crate
+/// * `x5u` - A URL of the X.509 certificate chain of the signing key, never fetched by this crate
+/// * `x5c` - The X.509 certificate chain of the signing key, leaf first, as standard base64 DER
+/// * `x5t` - The base64url SHA-1 thumbprint of the leaf certificate
+/// * `x5t_s256` - The base64url SHA-256 thumbprint of the leaf certificate, `x5t#S256` in the header
+/// * `crit` - The extension parameters the recipient must understand, tokens with any are rejected by `verify`
+/// * `extra` - Any other parameters, e.g. private parameters such as `tenant`, kept exactly as they appear in the header
+///
+/// # Example
+/// ```rust
+/// use super_simple_jwt::{decode_header, encode_header, Algorithm, Header};
+///
+/// let mut header = Header::new(Algorithm::ES256);
+/// header.extra.insert("tenant".to_string(), serde_json::json!("acme"));
+///
+/// let decoded = decode_header(&encode_header(&header).unwrap()).unwrap();
+/// assert_eq!(decoded.extra["tenant"], "acme");
+/// ```
 pub struct Header {
     pub alg: Algorithm,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cty: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jku: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwk: Option<Jwk>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x5u: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x5c: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x5t: Option<String>,
+    #[serde(rename = "x5t#S256", skip_serializing_if = "Option::is_none")]
+    pub x5t_s256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crit: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 impl Header {
 
     /// Creates a new JWT header with the specified algorithm.
+    ///
+    /// `typ` is set to `JWT`, and every other parameter is unset.
     /// 
     /// # Arguments
     /// * - `algorithm`: The algorithm to use for signing the JWT.
@@ -21,7 +78,7 @@ impl Header {
     /// 
     /// # Example
     /// ```rust
-    /// use jwt::model::header::{Header, Algorithm};
+    /// use super_simple_jwt::{Header, Algorithm};
     /// 
     /// let algorithm = Algorithm::HS256;
     /// let header = Header::new(algorithm);
@@ -29,31 +86,79 @@ impl Header {
     pub fn new(algorithm: Algorithm) -> Self {
         Header {
             alg: algorithm,
+            typ: Some("JWT".to_string()),
+            cty: None,
+            kid: None,
+            jku: None,
+            jwk: None,
+            x5u: None,
+            x5c: None,
+            x5t: None,
+            x5t_s256: None,
+            crit: None,
+            extra: Map::new(),
         }
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 /// The algorithm used to sign the JWT.
-/// Currently, only HS256 is supported.
+///
+/// The variants are the same with or without features, and more may be added, so matches need a wildcard arm.
+/// 
+/// * `RS256`/`RS384`/`RS512` - RSASSA-PKCS1-v1_5 using SHA-256/SHA-384/SHA-512
+/// * `PS256`/`PS384`/`PS512` - RSASSA-PSS using SHA-256/SHA-384/SHA-512
+/// * `ES256`/`ES384`/`ES512` - ECDSA using P-256 with SHA-256, P-384 with SHA-384 and P-521 with SHA-512
+/// * `ES256K` - ECDSA using secp256k1 with SHA-256, as defined in RFC 8812
+/// * `EdDSA` - EdDSA using Ed25519 or Ed448, as defined in RFC 8037. The curve is chosen by the key
+/// * `HS256`/`HS384`/`HS512` - HMAC using a shared secret with SHA-256/SHA-384/SHA-512
+/// * `None` - No signature at all, only read from a header and signed with the `dangerous-unsecured` feature, and never accepted by `verify`
 /// 
 /// # Example
 /// ```rust
-/// use jwt::model::header::Algorithm;
+/// use super_simple_jwt::{Header, Algorithm};
 /// 
 /// let algorithm = Algorithm::HS256;
 /// let header = Header::new(algorithm);
 /// ```
 pub enum Algorithm {
     RS256,
+    RS384,
     RS512,
+    PS256,
+    PS384,
+    PS512,
+    ES256,
+    ES384,
+    ES512,
+    ES256K,
+    EdDSA,
+    HS256,
+    HS384,
+    HS512,
+    #[serde(rename = "none")]
+    None,
 }
 
 impl Display for Algorithm {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Algorithm::RS256 => write!(f, "RS256"),
+            Algorithm::RS384 => write!(f, "RS384"),
             Algorithm::RS512 => write!(f, "RS512"),
+            Algorithm::PS256 => write!(f, "PS256"),
+            Algorithm::PS384 => write!(f, "PS384"),
+            Algorithm::PS512 => write!(f, "PS512"),
+            Algorithm::ES256 => write!(f, "ES256"),
+            Algorithm::ES384 => write!(f, "ES384"),
+            Algorithm::ES512 => write!(f, "ES512"),
+            Algorithm::ES256K => write!(f, "ES256K"),
+            Algorithm::EdDSA => write!(f, "EdDSA"),
+            Algorithm::HS256 => write!(f, "HS256"),
+            Algorithm::HS384 => write!(f, "HS384"),
+            Algorithm::HS512 => write!(f, "HS512"),
+            Algorithm::None => write!(f, "none"),
         }
     }
 }
@@ -64,7 +169,21 @@ impl FromStr for Algorithm {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "RS256" => Ok(Algorithm::RS256),
+            "RS384" => Ok(Algorithm::RS384),
             "RS512" => Ok(Algorithm::RS512),
+            "PS256" => Ok(Algorithm::PS256),
+            "PS384" => Ok(Algorithm::PS384),
+            "PS512" => Ok(Algorithm::PS512),
+            "ES256" => Ok(Algorithm::ES256),
+            "ES384" => Ok(Algorithm::ES384),
+            "ES512" => Ok(Algorithm::ES512),
+            "ES256K" => Ok(Algorithm::ES256K),
+            "EdDSA" => Ok(Algorithm::EdDSA),
+            "HS256" => Ok(Algorithm::HS256),
+            "HS384" => Ok(Algorithm::HS384),
+            "HS512" => Ok(Algorithm::HS512),
+            #[cfg(feature = "dangerous-unsecured")]
+            "none" => Ok(Algorithm::None),
             _ => Err(HeaderDecodeError::UnsupportedAlgorithm(s.to_string())),
         }
     }
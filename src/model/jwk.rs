@@ -0,0 +1,287 @@
+use std::fmt::{Debug, Formatter};
+
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use zeroize::Zeroizing;
+use sha2::{Digest, Sha256};
+
+use crate::encoding::ENCODING_ENGINE;
+
+/// The prefix of a SHA-256 JWK thumbprint URI, from RFC 9278
+const THUMBPRINT_URI_PREFIX: &str = "urn:ietf:params:oauth:jwk-thumbprint:sha-256:";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// A JSON Web Key, as defined in RFC 7517
+///
+/// Convert it to a key for signing or verifying with `EncodingKey::from_jwk` and `DecodingKey::from_jwk`,
+/// and back with `to_jwk`. Members this crate does not know are ignored when reading.
+///
+/// # Fields
+/// * `key` - The key material, which depends on the key type (`kty`)
+/// * `kid` - The key ID, used to pick a key from a set
+/// * `key_use` - What the key is used for, `sig` or `enc`, `use` in the JWK
+/// * `key_ops` - The operations the key may be used for, e.g. `sign` and `verify`
+/// * `alg` - The algorithm the key is used with
+/// * `x5u` - A URL of the X.509 certificate chain of the key
+/// * `x5c` - The X.509 certificate chain of the key, leaf first, as standard base64 DER
+/// * `x5t` - The base64url SHA-1 thumbprint of the leaf certificate
+/// * `x5t_s256` - The base64url SHA-256 thumbprint of the leaf certificate, `x5t#S256` in the JWK
+///
+/// # Example
+/// ```rust
+/// use super_simple_jwt::{DecodingKey, Jwk};
+///
+/// let jwk: Jwk = serde_json::from_str(r#"{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo","kid":"ed"}"#).unwrap();
+/// let key = DecodingKey::from_jwk(&jwk).unwrap();
+/// ```
+pub struct Jwk {
+    #[serde(flatten)]
+    pub key: JwkKey,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+    pub key_use: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_ops: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x5u: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x5c: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x5t: Option<String>,
+    #[serde(rename = "x5t#S256", skip_serializing_if = "Option::is_none")]
+    pub x5t_s256: Option<String>,
+}
+
+impl Jwk {
+
+    /// Creates a JWK from key material, with no other members set
+    ///
+    /// # Arguments
+    /// * `key` - The key material
+    ///
+    /// # Returns
+    /// * A new `Jwk` with the key material
+    pub fn new(key: JwkKey) -> Self {
+        Jwk {
+            key,
+            kid: None,
+            key_use: None,
+            key_ops: None,
+            alg: None,
+            x5u: None,
+            x5c: None,
+            x5t: None,
+            x5t_s256: None,
+        }
+    }
+
+    /// Whether the JWK holds a private key or shared secret
+    pub fn is_private(&self) -> bool {
+        match &self.key {
+            JwkKey::Rsa { d, .. } | JwkKey::Ec { d, .. } | JwkKey::Okp { d, .. } => d.is_some(),
+            JwkKey::Oct { .. } => true,
+        }
+    }
+
+    /// Returns the JWK with the private key removed, keeping every other member
+    ///
+    /// # Returns
+    /// * `Option<Jwk>` - The public JWK, or `None` for shared secrets, which have no public part
+    pub fn to_public(&self) -> Option<Jwk> {
+        match self.key {
+            JwkKey::Oct { .. } => None,
+            _ => Some(Jwk { key: self.key.without_private(), ..self.clone() }),
+        }
+    }
+
+    /// Computes the SHA-256 thumbprint of the key, as defined in RFC 7638
+    ///
+    /// The thumbprint only covers the required public members of the key, so a private JWK has the same thumbprint as its public JWK.
+    /// It is stable and derived from the key, which makes it a good `kid`.
+    ///
+    /// # Returns
+    /// * `String` - The base64url encoded thumbprint
+    pub fn thumbprint(&self) -> String {
+        // The required members in lexicographic order, serialized without whitespace
+        let members = match &self.key {
+            JwkKey::Rsa { n, e, .. } => json!({ "e": e, "kty": "RSA", "n": n }),
+            JwkKey::Ec { crv, x, y, .. } => json!({ "crv": crv, "kty": "EC", "x": x, "y": y }),
+            JwkKey::Okp { crv, x, .. } => json!({ "crv": crv, "kty": "OKP", "x": x }),
+            JwkKey::Oct { k } => json!({ "k": k, "kty": "oct" }),
+        };
+        ENCODING_ENGINE.encode(Sha256::digest(members.to_string()))
+    }
+
+    /// The SHA-256 thumbprint of the key as a URI, as defined in RFC 9278
+    ///
+    /// # Returns
+    /// * `String` - The URI, `urn:ietf:params:oauth:jwk-thumbprint:sha-256:` followed by the thumbprint
+    pub fn thumbprint_uri(&self) -> String {
+        format!("{}{}", THUMBPRINT_URI_PREFIX, self.thumbprint())
+    }
+
+    /// Whether the `use` and `key_ops` members, when present, allow the key to be used for an operation
+    pub(crate) fn allows(&self, operation: &str) -> bool {
+        let use_allowed = self.key_use.as_deref().is_none_or(|key_use| key_use == "sig");
+        let ops_allowed = self.key_ops.as_ref().is_none_or(|key_ops| key_ops.iter().any(|op| op == operation));
+        use_allowed && ops_allowed
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "kty")]
+/// The key material of a JWK, tagged by its key type (`kty`)
+///
+/// Every value is base64url encoded without padding, as in the JWK. The private members are `None` for public keys.
+/// The private members and shared secrets are wiped from memory when dropped, and `Debug` does not show them.
+///
+/// # Variants
+/// * `Rsa` - An RSA key, as defined in RFC 7518. Only `d` is needed for a private key, the primes are recovered if missing
+/// * `Ec` - An EC key on `P-256`, `P-384`, `P-521` or `secp256k1`, as defined in RFC 7518 and RFC 8812
+/// * `Okp` - An `Ed25519` or `Ed448` key, as defined in RFC 8037
+/// * `Oct` - A shared secret for the HMAC algorithms
+pub enum JwkKey {
+    #[serde(rename = "RSA")]
+    Rsa {
+        n: String,
+        e: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        d: Option<Zeroizing<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        p: Option<Zeroizing<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        q: Option<Zeroizing<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        dp: Option<Zeroizing<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        dq: Option<Zeroizing<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        qi: Option<Zeroizing<String>>,
+    },
+    #[serde(rename = "EC")]
+    Ec {
+        crv: String,
+        x: String,
+        y: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        d: Option<Zeroizing<String>>,
+    },
+    #[serde(rename = "OKP")]
+    Okp {
+        crv: String,
+        x: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        d: Option<Zeroizing<String>>,
+    },
+    #[serde(rename = "oct")]
+    Oct {
+        k: Zeroizing<String>,
+    },
+}
+
+impl JwkKey {
+
+    /// The key material with the private members removed, shared secrets are returned unchanged
+    pub(crate) fn without_private(&self) -> JwkKey {
+        match self {
+            JwkKey::Rsa { n, e, .. } => JwkKey::Rsa { n: n.clone(), e: e.clone(), d: None, p: None, q: None, dp: None, dq: None, qi: None },
+            JwkKey::Ec { crv, x, y, .. } => JwkKey::Ec { crv: crv.clone(), x: x.clone(), y: y.clone(), d: None },
+            JwkKey::Okp { crv, x, .. } => JwkKey::Okp { crv: crv.clone(), x: x.clone(), d: None },
+            JwkKey::Oct { k } => JwkKey::Oct { k: k.clone() },
+        }
+    }
+}
+
+impl Debug for JwkKey {
+    /// Shows the public members, the private members and shared secrets are redacted
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let redacted = |member: &Option<Zeroizing<String>>| member.as_ref().map(|_| "<redacted>");
+        match self {
+            JwkKey::Rsa { n, e, d, p, q, dp, dq, qi } => f.debug_struct("Rsa")
+                .field("n", n)
+                .field("e", e)
+                .field("d", &redacted(d))
+                .field("p", &redacted(p))
+                .field("q", &redacted(q))
+                .field("dp", &redacted(dp))
+                .field("dq", &redacted(dq))
+                .field("qi", &redacted(qi))
+                .finish(),
+            JwkKey::Ec { crv, x, y, d } => f.debug_struct("Ec").field("crv", crv).field("x", x).field("y", y).field("d", &redacted(d)).finish(),
+            JwkKey::Okp { crv, x, d } => f.debug_struct("Okp").field("crv", crv).field("x", x).field("d", &redacted(d)).finish(),
+            JwkKey::Oct { .. } => f.debug_struct("Oct").field("k", &"<redacted>").finish(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+/// A JWK Set, as defined in RFC 7517 section 5, e.g. the document served at an identity provider's `jwks_uri`
+///
+/// Keys of a type this crate does not support are skipped when deserializing, as RFC 7517 recommends.
+/// A key of a supported type that cannot be deserialized, e.g. with a member missing, is an error rather than being left out.
+/// Read its keys into a `DecodingKeySet` to verify tokens with `verify_with_jwks`, which picks the key by the `kid` of the token.
+///
+/// # Fields
+/// * `keys` - The keys in the set
+///
+/// # Example
+/// ```rust,no_run
+/// use super_simple_jwt::{verify_with_jwks, DecodingKeySet, JwkSet};
+/// # #[derive(serde::Serialize, serde::Deserialize, Clone)]
+/// # struct Claims { exp: usize, sub: String }
+///
+/// let json = std::fs::read_to_string("jwks.json").unwrap();
+/// let jwks: JwkSet = serde_json::from_str(&json).unwrap();
+/// let keys = DecodingKeySet::from_jwk_set(&jwks).unwrap();
+///
+/// let claims: Claims = verify_with_jwks("pretend_this.is_a.valid_signed_token", &keys).unwrap();
+/// ```
+pub struct JwkSet {
+    #[serde(deserialize_with = "deserialize_supported_keys")]
+    pub keys: Vec<Jwk>,
+}
+
+impl JwkSet {
+
+    /// Creates a JWK Set from keys
+    ///
+    /// # Arguments
+    /// * `keys` - The keys in the set
+    ///
+    /// # Returns
+    /// * A new `JwkSet` with the keys
+    pub fn new(keys: Vec<Jwk>) -> Self {
+        JwkSet { keys }
+    }
+
+    /// Finds the keys with a key ID
+    ///
+    /// # Arguments
+    /// * `kid` - The key ID
+    ///
+    /// # Returns
+    /// * An iterator over the keys with the key ID, usually at most one
+    pub fn find<'a>(&'a self, kid: &'a str) -> impl Iterator<Item = &'a Jwk> + 'a {
+        self.keys.iter().filter(move |jwk| jwk.kid.as_deref() == Some(kid))
+    }
+}
+
+/// The `kty` values of `JwkKey`, keys of any other type are skipped when reading a set
+const SUPPORTED_KEY_TYPES: [&str; 4] = ["RSA", "EC", "OKP", "oct"];
+
+/// Deserializes the keys of a set, skipping those of a key type that is not supported
+fn deserialize_supported_keys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Jwk>, D::Error> {
+    let keys = Vec::<Value>::deserialize(deserializer)?;
+    keys.into_iter()
+        .filter(|key| match key.get("kty").and_then(Value::as_str) {
+            Some(kty) => SUPPORTED_KEY_TYPES.contains(&kty),
+            // A key without a `kty` is malformed rather than of another type
+            None => true,
+        })
+        .map(|key| serde_json::from_value(key).map_err(serde::de::Error::custom))
+        .collect()
+}
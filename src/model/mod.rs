@@ -1 +1,2 @@
-pub mod header;
\ No newline at end of file
+pub mod header;
+pub mod jwk;
@@ -0,0 +1,66 @@
+use p256::ecdsa::signature::Signer;
+
+use crate::keys::{check_curve, EcCurve};
+use crate::keys::encoding_key::{EncodingKey, EncodingKeyKind};
+use crate::model::header::Algorithm;
+
+use super::SigningError;
+
+/// This function signs a JWT using ECDSA with a private key in PEM format.
+///
+/// The signature is the fixed width `R || S` encoding required by RFC 7518, not DER.
+///
+/// # Arguments
+/// * `header` - A reference to the base64 encoded JWT header as a string.
+/// * `body` - A reference to the base64 encoded JWT body as a string.
+/// * `key_from_pem` - A reference to the private key in SEC1 (`EC PRIVATE KEY`) or PKCS#8 (`PRIVATE KEY`) PEM format as a string.
+/// * `algorithm` - The ECDSA algorithm to use, one of `ES256` (P-256), `ES384` (P-384), `ES512` (P-521) or `ES256K` (secp256k1).
+///
+/// # Returns
+/// A string representing the signed JWT.
+pub fn sign_ecdsa(header: &str, body: &str, key_from_pem: &str, algorithm: Algorithm) -> Result<String, SigningError> {
+    let key = EncodingKey::for_algorithm(key_from_pem, &algorithm).map_err(|err| SigningError::InvalidKey(err.to_string()))?;
+    super::sign_encoded(header, body, |data| signature(data, &key, &algorithm))
+}
+
+
+/// This function signs a JWT signing input using ECDSA with an already parsed key.
+///
+/// # Arguments
+/// * `data` - The signing input, the base64 encoded header and body joined with a dot.
+/// * `key` - The EC private key, which must be for the curve of the algorithm.
+/// * `algorithm` - The ECDSA algorithm to use, one of `ES256` (P-256), `ES384` (P-384), `ES512` (P-521) or `ES256K` (secp256k1).
+///
+/// # Returns
+/// The bytes of the signature.
+pub fn signature(data: &[u8], key: &EncodingKey, algorithm: &Algorithm) -> Result<Vec<u8>, SigningError> {
+    if EcCurve::for_algorithm(algorithm).is_none() {
+        return Err(SigningError::InvalidAlgorithm(algorithm.to_string()));
+    }
+
+    // Check the key is for the curve of the algorithm and sign the data
+    // The key and signature types differ per curve, so this must be a match statement
+    match key.kind.as_ref() {
+        EncodingKeyKind::P256(signing_key) => {
+            check_curve(EcCurve::P256, algorithm).map_err(|err| SigningError::InvalidKey(err.to_string()))?;
+            let signature: p256::ecdsa::Signature = signing_key.try_sign(data).map_err(|err| SigningError::InvalidData(err.to_string()))?;
+            Ok(signature.to_bytes().to_vec())
+        }
+        EncodingKeyKind::P384(signing_key) => {
+            check_curve(EcCurve::P384, algorithm).map_err(|err| SigningError::InvalidKey(err.to_string()))?;
+            let signature: p384::ecdsa::Signature = signing_key.try_sign(data).map_err(|err| SigningError::InvalidData(err.to_string()))?;
+            Ok(signature.to_bytes().to_vec())
+        }
+        EncodingKeyKind::P521(signing_key) => {
+            check_curve(EcCurve::P521, algorithm).map_err(|err| SigningError::InvalidKey(err.to_string()))?;
+            let signature: p521::ecdsa::Signature = signing_key.try_sign(data).map_err(|err| SigningError::InvalidData(err.to_string()))?;
+            Ok(signature.to_bytes().to_vec())
+        }
+        EncodingKeyKind::Secp256k1(signing_key) => {
+            check_curve(EcCurve::Secp256k1, algorithm).map_err(|err| SigningError::InvalidKey(err.to_string()))?;
+            let signature: k256::ecdsa::Signature = signing_key.try_sign(data).map_err(|err| SigningError::InvalidData(err.to_string()))?;
+            Ok(signature.to_bytes().to_vec())
+        }
+        _ => Err(SigningError::InvalidKey(format!("{} requires an EC key", algorithm))),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,46 @@
+use ed25519_dalek::Signer;
+
+use crate::keys::encoding_key::{EncodingKey, EncodingKeyKind};
+use crate::model::header::Algorithm;
+
+use super::SigningError;
+
+/// This function signs a JWT using EdDSA, as defined in RFC 8037.
+///
+/// The curve, Ed25519 or Ed448, is chosen by the key.
+///
+/// # Arguments
+/// * `header` - A reference to the base64 encoded JWT header as a string.
+/// * `body` - A reference to the base64 encoded JWT body as a string.
+/// * `key` - A reference to the private key, either in PKCS#8 PEM format or as an OKP JWK.
+/// * `algorithm` - The algorithm to use, which must be `EdDSA`.
+///
+/// # Returns
+/// A string representing the signed JWT.
+pub fn sign_eddsa(header: &str, body: &str, key: &str, algorithm: Algorithm) -> Result<String, SigningError> {
+    let key = EncodingKey::for_algorithm(key, &Algorithm::EdDSA).map_err(|err| SigningError::InvalidKey(err.to_string()))?;
+    super::sign_encoded(header, body, |data| signature(data, &key, &algorithm))
+}
+
+
+/// This function signs a JWT signing input using EdDSA with an already parsed key.
+///
+/// # Arguments
+/// * `data` - The signing input, the base64 encoded header and body joined with a dot.
+/// * `key` - The Ed25519 or Ed448 private key.
+/// * `algorithm` - The algorithm to use, which must be `EdDSA`.
+///
+/// # Returns
+/// The bytes of the signature.
+pub fn signature(data: &[u8], key: &EncodingKey, algorithm: &Algorithm) -> Result<Vec<u8>, SigningError> {
+    if *algorithm != Algorithm::EdDSA {
+        return Err(SigningError::InvalidAlgorithm(algorithm.to_string()));
+    }
+
+    // Sign the data with the curve of the key
+    match key.kind.as_ref() {
+        EncodingKeyKind::Ed25519(signing_key) => Ok(signing_key.sign(data).to_bytes().to_vec()),
+        EncodingKeyKind::Ed448(signing_key) => Ok(signing_key.sign_raw(data).to_bytes().to_vec()),
+        _ => Err(SigningError::InvalidKey(format!("{} requires an Ed25519 or Ed448 key", algorithm))),
+    }
+}
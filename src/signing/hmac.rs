@@ -0,0 +1,57 @@
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha384, Sha512};
+
+use crate::keys::encoding_key::{EncodingKey, EncodingKeyKind};
+use crate::keys::policy::check_hmac_length;
+use crate::model::header::Algorithm;
+
+use super::SigningError;
+
+/// This function signs a JWT using HMAC with a shared secret.
+///
+/// # Arguments
+/// * `header` - A reference to the base64 encoded JWT header as a string.
+/// * `body` - A reference to the base64 encoded JWT body as a string.
+/// * `secret` - A reference to the shared secret as a string.
+/// * `algorithm` - The HMAC algorithm to use, one of `HS256`, `HS384` or `HS512`.
+///
+/// # Returns
+/// A string representing the signed JWT.
+pub fn hmac_sha(header: &str, body: &str, secret: &str, algorithm: Algorithm) -> Result<String, SigningError> {
+    let key = EncodingKey::from_secret(secret.as_bytes()).map_err(|err| SigningError::InvalidKey(err.to_string()))?;
+    super::sign_encoded(header, body, |data| signature(data, &key, &algorithm))
+}
+
+
+/// This function computes the MAC of a JWT signing input with a shared secret key.
+///
+/// # Arguments
+/// * `data` - The signing input, the base64 encoded header and body joined with a dot.
+/// * `key` - The shared secret key.
+/// * `algorithm` - The HMAC algorithm to use, one of `HS256`, `HS384` or `HS512`.
+///
+/// # Returns
+/// The bytes of the MAC.
+pub fn signature(data: &[u8], key: &EncodingKey, algorithm: &Algorithm) -> Result<Vec<u8>, SigningError> {
+    let EncodingKeyKind::Hmac(secret) = key.kind.as_ref() else {
+        return Err(SigningError::InvalidKey(format!("{} requires a shared secret", algorithm)));
+    };
+    if key.hmac_hash_length {
+        check_hmac_length(secret, algorithm).map_err(SigningError::InvalidKey)?;
+    }
+
+    // Compute the MAC with the correct hash function
+    match algorithm {
+        Algorithm::HS256 => compute::<Hmac<Sha256>>(secret, data),
+        Algorithm::HS384 => compute::<Hmac<Sha384>>(secret, data),
+        Algorithm::HS512 => compute::<Hmac<Sha512>>(secret, data),
+        _ => Err(SigningError::InvalidAlgorithm(algorithm.to_string())),
+    }
+}
+
+/// Computes the MAC of `data` using the given secret
+fn compute<M: Mac + hmac::digest::KeyInit>(secret: &[u8], data: &[u8]) -> Result<Vec<u8>, SigningError> {
+    let mut mac = <M as Mac>::new_from_slice(secret).map_err(|err| SigningError::InvalidKey(err.to_string()))?;
+    mac.update(data);
+    Ok(mac.finalize().into_bytes().to_vec())
+}
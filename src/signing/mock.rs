@@ -0,0 +1,99 @@
+use std::sync::Mutex;
+
+use crate::keys::decoding_key::DecodingKey;
+use crate::keys::encoding_key::EncodingKey;
+use crate::keys::KeyError;
+use crate::model::header::Algorithm;
+
+use super::signer::Signer;
+use super::SigningError;
+
+/// A `Signer` that signs in process, standing in for a remote key service in tests
+///
+/// It signs with a generated or given key, records every signing input it is given, and can be told to fail
+/// to check how the caller handles an unavailable backend. Tokens it signs verify with `verifying_key()`.
+/// It is only exported with the `test-util` feature, to keep it out of the public API of normal builds.
+///
+/// # Example
+/// ```rust
+/// use super_simple_jwt::{sign_with_key, verify_with_key, Algorithm, Header, MockSigner};
+/// # #[derive(serde::Serialize, serde::Deserialize, Clone)]
+/// # struct Claims { exp: usize, sub: String }
+///
+/// let signer = MockSigner::generate(&Algorithm::ES256).unwrap().with_kid("mock-key");
+/// let claims = Claims { exp: 1700000000, sub: "user".to_string() };
+///
+/// let token = sign_with_key(&Header::new(Algorithm::ES256), &claims, &signer).unwrap();
+/// let verified: Claims = verify_with_key(&token, &signer.verifying_key()).unwrap();
+/// assert_eq!(signer.signing_inputs().len(), 1);
+/// ```
+pub struct MockSigner {
+    key: EncodingKey,
+    failure: Option<String>,
+    signing_inputs: Mutex<Vec<Vec<u8>>>,
+}
+
+impl MockSigner {
+
+    /// Creates a mock signer with a key
+    ///
+    /// # Arguments
+    /// * `key` - The key that signs tokens
+    ///
+    /// # Returns
+    /// * A new `MockSigner`
+    pub fn new(key: EncodingKey) -> Self {
+        MockSigner {
+            key,
+            failure: None,
+            signing_inputs: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Creates a mock signer with a new random key for an algorithm
+    ///
+    /// # Arguments
+    /// * `algorithm` - The algorithm the key is for
+    ///
+    /// # Returns
+    /// * `Result<MockSigner, KeyError>` - The mock signer, or an error if a key cannot be generated for the algorithm
+    pub fn generate(algorithm: &Algorithm) -> Result<Self, KeyError> {
+        Ok(Self::new(EncodingKey::generate(algorithm)?))
+    }
+
+    /// Sets the key ID, put in the `kid` of tokens whose header has none
+    pub fn with_kid(mut self, kid: impl Into<String>) -> Self {
+        self.key = self.key.with_kid(kid);
+        self
+    }
+
+    /// Makes every signing request fail with `SigningError::Signer` and the message, as an unavailable backend would
+    pub fn with_failure(mut self, message: impl Into<String>) -> Self {
+        self.failure = Some(message.into());
+        self
+    }
+
+    /// The key that verifies the tokens it signs
+    pub fn verifying_key(&self) -> DecodingKey {
+        self.key.to_decoding_key()
+    }
+
+    /// The signing inputs it was asked to sign, in order, including those that failed
+    pub fn signing_inputs(&self) -> Vec<Vec<u8>> {
+        self.signing_inputs.lock().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+}
+
+impl Signer for MockSigner {
+    fn sign(&self, signing_input: &[u8], algorithm: &Algorithm) -> Result<Vec<u8>, SigningError> {
+        self.signing_inputs.lock().unwrap_or_else(|err| err.into_inner()).push(signing_input.to_vec());
+        if let Some(message) = &self.failure {
+            return Err(SigningError::Signer(message.clone()));
+        }
+        Signer::sign(&self.key, signing_input, algorithm)
+    }
+
+    fn kid(&self) -> Option<&str> {
+        self.key.kid()
+    }
+}
@@ -1,15 +1,31 @@
+use std::borrow::Cow;
+use std::time::SystemTime;
+
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 
-use crate::{encoding, signing, model::header::Header};
+use crate::{encoding, signing, model::header::{Algorithm, Header}};
+use crate::encoding::ENCODING_ENGINE;
+use crate::keys::key_ring::KeyRing;
+
+use self::signer::{AsyncSigner, Signer};
 
+pub mod ecdsa;
+pub mod eddsa;
+pub mod hmac;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
 pub mod rsa;
+pub mod signer;
 
 /// Signs the token with the given header and claims using the specified signing key.
 /// 
 /// # Arguments
 /// * `header` - The header of the JWT
 /// * `claims` - The claims to be included in the token, which can be any serializable type.
-/// * `signing_key` - The key used to sign the token, read from the file directly as a string
+/// * `signing_key` - The key used to sign the token, read from the file directly as a string, or the shared secret for HMAC algorithms
 /// * `T` - The type of the claims, which must implement `serde::Serialize`, `serde::Deserialize` and `Clone`.
 /// 
 /// # Returns
@@ -20,18 +36,120 @@ pub fn sign<'a, T: Serialize + Deserialize<'a> + Clone>(header: &Header, claims:
     let algorithm = header.alg.clone();
 
     // Encode the header and claims
-    let encoded_header = encoding::header::encode(header).map_err(|_| SigningError::InvalidData(format!("Failed to encode header")))?;
-    let encoded_claims = encoding::claims::encode(claims).map_err(|_| SigningError::InvalidData(format!("Failed to encode claims")))?;
+    let encoded_header = encoding::header::encode(header).map_err(|_| SigningError::InvalidData("Failed to encode header".to_string()))?;
+    let encoded_claims = encoding::claims::encode(claims).map_err(|_| SigningError::InvalidData("Failed to encode claims".to_string()))?;
 
     // Sign the token using the correct algorithm 
-    let signed_token = signing::rsa::hmac_rsa(&encoded_header, &encoded_claims, signing_key, algorithm)?;
+    let signed_token = match algorithm {
+        Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512 => signing::rsa::hmac_rsa(&encoded_header, &encoded_claims, signing_key, algorithm)?,
+        Algorithm::ES256 | Algorithm::ES384 | Algorithm::ES512 | Algorithm::ES256K => signing::ecdsa::sign_ecdsa(&encoded_header, &encoded_claims, signing_key, algorithm)?,
+        Algorithm::EdDSA => signing::eddsa::sign_eddsa(&encoded_header, &encoded_claims, signing_key, algorithm)?,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => signing::hmac::hmac_sha(&encoded_header, &encoded_claims, signing_key, algorithm)?,
+        // Unsecured tokens can only be created through `dangerous_encode_unsecured`
+        Algorithm::None => return Err(SigningError::InvalidAlgorithm(algorithm.to_string())),
+    };
     Ok(signed_token)
 }
 
+/// Signs the token with the given header and claims using an already parsed key, or any other `Signer`.
+///
+/// The key is read once with `EncodingKey` and can be reused for any number of tokens.
+/// The key must match the algorithm in the header, e.g. an RSA key for `RS256`.
+/// A `Signer` can also sign with a key that is held elsewhere, e.g. in a hardware security module or a cloud key service.
+/// If the key has a key ID, e.g. from `EncodingKey::with_thumbprint_kid`, and the header has no `kid`, the key ID is put in the header.
+///
+/// # Arguments
+/// * `header` - The header of the JWT
+/// * `claims` - The claims to be included in the token, which can be any serializable type.
+/// * `key` - The key used to sign the token
+/// * `T` - The type of the claims, which must implement `serde::Serialize`, `serde::Deserialize` and `Clone`.
+///
+/// # Returns
+/// * `Result<String, SigningError>` - The encoded & signed token as a string, or an error if signing fails.
+pub fn sign_with_key<'a, T: Serialize + Deserialize<'a> + Clone>(header: &Header, claims: &T, key: &(impl Signer + ?Sized)) -> Result<String, SigningError> {
+    let signing_input = signing_input(header, claims, key.kid())?;
+
+    // Sign the header and claims, the key must be of the type the algorithm requires
+    let signature = key.sign(signing_input.as_bytes(), &header.alg)?;
+    Ok(format!("{}.{}", signing_input, ENCODING_ENGINE.encode(signature)))
+}
+
+/// Signs the token with an `AsyncSigner`, e.g. a cloud key service that is called over the network
+///
+/// Behaves as `sign_with_key` once the signature is returned. Any `Signer`, including `EncodingKey`, can also be used.
+///
+/// # Arguments
+/// * `header` - The header of the JWT
+/// * `claims` - The claims to be included in the token, which can be any serializable type.
+/// * `signer` - The signer that signs the token
+/// * `T` - The type of the claims, which must implement `serde::Serialize`, `serde::Deserialize` and `Clone`.
+///
+/// # Returns
+/// * `Result<String, SigningError>` - The encoded & signed token as a string, or an error if signing fails.
+pub async fn sign_async<'a, T: Serialize + Deserialize<'a> + Clone>(header: &Header, claims: &T, signer: &(impl AsyncSigner + ?Sized)) -> Result<String, SigningError> {
+    let signing_input = signing_input(header, claims, AsyncSigner::kid(signer))?;
+    let signature = AsyncSigner::sign(signer, signing_input.as_bytes(), &header.alg).await?;
+    Ok(format!("{}.{}", signing_input, ENCODING_ENGINE.encode(signature)))
+}
+
+/// Signs the token with the active key of a key ring
+///
+/// The `kid` of the header is always set to the key ID of the active key, so verifiers can pick the right key during rotation.
+/// The algorithm in the header must match the active key, e.g. an EC P-256 key for `ES256`.
+///
+/// # Arguments
+/// * `header` - The header of the JWT
+/// * `claims` - The claims to be included in the token, which can be any serializable type.
+/// * `key_ring` - The key ring whose active key signs the token
+/// * `T` - The type of the claims, which must implement `serde::Serialize`, `serde::Deserialize` and `Clone`.
+///
+/// # Returns
+/// * `Result<String, SigningError>` - The encoded & signed token as a string, or `InvalidKey` if the active key is outside its validity window
+pub fn sign_with_key_ring<'a, T: Serialize + Deserialize<'a> + Clone>(header: &Header, claims: &T, key_ring: &KeyRing) -> Result<String, SigningError> {
+    let kid = key_ring.active_kid();
+
+    // The active key may have been given a validity window, e.g. one that has not started yet
+    if key_ring.get(kid).is_some_and(|entry| !entry.is_valid_at(SystemTime::now())) {
+        return Err(SigningError::InvalidKey(format!("The active key '{}' is outside its validity window", kid)));
+    }
+
+    let header = Header { kid: Some(kid.to_string()), ..header.clone() };
+    sign_with_key(&header, claims, key_ring.signing_key())
+}
+
+/// Joins an already encoded header and claims with the signature `sign` computes over them, for the functions `sign` uses
+pub(crate) fn sign_encoded(header: &str, body: &str, sign: impl FnOnce(&[u8]) -> Result<Vec<u8>, SigningError>) -> Result<String, SigningError> {
+    let signing_input = format!("{}.{}", header, body);
+    let signature = sign(signing_input.as_bytes())?;
+    Ok(format!("{}.{}", signing_input, ENCODING_ENGINE.encode(signature)))
+}
+
+/// Encodes the header and claims into the signing input, the part of the token that is signed
+///
+/// The key ID of the signer is put in the header, unless the header already names one.
+fn signing_input<T: Serialize>(header: &Header, claims: &T, kid: Option<&str>) -> Result<String, SigningError> {
+    // Unsecured tokens can only be created through `dangerous_encode_unsecured`
+    if header.alg == Algorithm::None {
+        return Err(SigningError::InvalidAlgorithm(header.alg.to_string()));
+    }
+
+    // Stamp the key ID of the key, unless the header already names one
+    let header = match (&header.kid, kid) {
+        (None, Some(kid)) => Cow::Owned(Header { kid: Some(kid.to_string()), ..header.clone() }),
+        _ => Cow::Borrowed(header),
+    };
+
+    // Encode the header and claims
+    let encoded_header = encoding::header::encode(&header).map_err(|_| SigningError::InvalidData("Failed to encode header".to_string()))?;
+    let encoded_claims = encoding::claims::encode(claims).map_err(|_| SigningError::InvalidData("Failed to encode claims".to_string()))?;
+    Ok(format!("{}.{}", encoded_header, encoded_claims))
+}
+
 #[derive(Debug)]
 /// The error type for signing operations
 pub enum SigningError {
     InvalidKey(String),
     InvalidData(String),
     InvalidAlgorithm(String),
+    Signer(String),
 }
\ No newline at end of file
@@ -0,0 +1,733 @@
+use std::ffi::c_void;
+use std::fmt::Display;
+use std::path::Path;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use libloading::Library;
+use pkcs8::der::Decode;
+use pkcs8::ObjectIdentifier;
+use rsa::{BigUint, RsaPublicKey};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::keys::decoding_key::{DecodingKey, DecodingKeyKind};
+use crate::keys::policy::KeyPolicy;
+use crate::keys::{check_curve, EcCurve};
+use crate::model::header::Algorithm;
+
+use self::ffi::{Attribute, FunctionList, Mechanism, PssParams, TokenInfo, CK_OBJECT_HANDLE, CK_RV, CK_SESSION_HANDLE, CK_SLOT_ID, CK_ULONG};
+use super::signer::Signer;
+use super::SigningError;
+
+/// A PKCS#11 module, the shared library of a hardware security module or of SoftHSM
+///
+/// The module is initialised when it is loaded and finalised when the last clone is dropped.
+/// Load each module once per process and clone it, as a module is shared by everything in the process that uses it.
+///
+/// # Example
+/// ```rust,no_run
+/// use super_simple_jwt::{sign_with_key, Algorithm, Header, Pkcs11Key, Pkcs11Module, Pkcs11Signer};
+/// # #[derive(serde::Serialize, serde::Deserialize, Clone)]
+/// # struct Claims { exp: usize, sub: String }
+/// # let claims = Claims { exp: 1700000000, sub: "user".to_string() };
+///
+/// let module = Pkcs11Module::load("/usr/lib/softhsm/libsofthsm2.so").unwrap();
+/// let signer = Pkcs11Signer::open(&module, "jwt-signing", "1234", Pkcs11Key::Label("jwt-ec".to_string())).unwrap();
+///
+/// let token = sign_with_key(&Header::new(Algorithm::ES256), &claims, &signer).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Pkcs11Module {
+    inner: Arc<ModuleInner>,
+}
+
+struct ModuleInner {
+    functions: *const FunctionList,
+    /// Whether this module initialised the library, and so must finalise it
+    initialized: bool,
+    // Must be dropped last, as the function list points into it
+    _library: Library,
+}
+
+// The module is initialised with `CKF_OS_LOCKING_OK`, so it may be called from any thread
+unsafe impl Send for ModuleInner {}
+unsafe impl Sync for ModuleInner {}
+
+impl Pkcs11Module {
+
+    /// Loads and initialises a PKCS#11 module
+    ///
+    /// # Arguments
+    /// * `path` - The path of the shared library, e.g. `/usr/lib/softhsm/libsofthsm2.so`
+    ///
+    /// # Returns
+    /// * `Result<Pkcs11Module, Pkcs11Error>` - The module, or an error if it cannot be loaded or initialised
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Pkcs11Error> {
+        let path = path.as_ref();
+        // Loading a library runs its initialisers, which is unavoidable for a PKCS#11 module
+        let library = unsafe { Library::new(path) }.map_err(|err| Pkcs11Error::Module(format!("Failed to load {}: {}", path.display(), err)))?;
+
+        let mut functions: *mut FunctionList = ptr::null_mut();
+        unsafe {
+            let get_function_list = library.get::<ffi::GetFunctionList>(b"C_GetFunctionList\0")
+                .map_err(|err| Pkcs11Error::Module(format!("{} is not a PKCS#11 module: {}", path.display(), err)))?;
+            check("C_GetFunctionList", get_function_list(&mut functions))?;
+        }
+        if functions.is_null() {
+            return Err(Pkcs11Error::Module(format!("{} returned no function list", path.display())));
+        }
+
+        let mut args = ffi::InitializeArgs {
+            create_mutex: ptr::null_mut(),
+            destroy_mutex: ptr::null_mut(),
+            lock_mutex: ptr::null_mut(),
+            unlock_mutex: ptr::null_mut(),
+            flags: ffi::CKF_OS_LOCKING_OK,
+            reserved: ptr::null_mut(),
+        };
+        let initialize = unsafe { (*functions).initialize }.ok_or_else(|| missing("C_Initialize"))?;
+        let rv = unsafe { initialize(&mut args as *mut ffi::InitializeArgs as *mut c_void) };
+        // Another library in the process may have initialised the module already
+        if rv != ffi::CKR_OK && rv != ffi::CKR_CRYPTOKI_ALREADY_INITIALIZED {
+            return Err(call_error("C_Initialize", rv));
+        }
+
+        Ok(Pkcs11Module {
+            inner: Arc::new(ModuleInner {
+                functions,
+                initialized: rv == ffi::CKR_OK,
+                _library: library,
+            }),
+        })
+    }
+
+    fn functions(&self) -> &FunctionList {
+        // The function list lives as long as the library, which the module owns
+        unsafe { &*self.inner.functions }
+    }
+
+    /// Finds the slot of the token with a label
+    fn find_slot(&self, token_label: &str) -> Result<CK_SLOT_ID, Pkcs11Error> {
+        let get_slot_list = self.functions().get_slot_list.ok_or_else(|| missing("C_GetSlotList"))?;
+        let get_token_info = self.functions().get_token_info.ok_or_else(|| missing("C_GetTokenInfo"))?;
+
+        let mut count: CK_ULONG = 0;
+        check("C_GetSlotList", unsafe { get_slot_list(ffi::CK_TRUE, ptr::null_mut(), &mut count) })?;
+        let mut slots: Vec<CK_SLOT_ID> = vec![0; count as usize];
+        check("C_GetSlotList", unsafe { get_slot_list(ffi::CK_TRUE, slots.as_mut_ptr(), &mut count) })?;
+        slots.truncate(count as usize);
+
+        for slot in slots {
+            let mut info = TokenInfo::default();
+            check("C_GetTokenInfo", unsafe { get_token_info(slot, &mut info) })?;
+            // Labels are padded with spaces to 32 bytes
+            let label = info.label;
+            if String::from_utf8_lossy(&label).trim_end() == token_label {
+                return Ok(slot);
+            }
+        }
+        Err(Pkcs11Error::TokenNotFound(token_label.to_string()))
+    }
+}
+
+impl Drop for ModuleInner {
+    fn drop(&mut self) {
+        if self.initialized && let Some(finalize) = unsafe { (*self.functions).finalize } {
+            unsafe { finalize(ptr::null_mut()) };
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// How the key of a `Pkcs11Signer` is found on the token
+///
+/// # Variants
+/// * `Label` - The key with a `CKA_LABEL`
+/// * `Id` - The key with a `CKA_ID`
+pub enum Pkcs11Key {
+    /// The key with a `CKA_LABEL`
+    Label(String),
+    /// The key with a `CKA_ID`
+    Id(Vec<u8>),
+}
+
+impl Display for Pkcs11Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pkcs11Key::Label(label) => write!(f, "label '{}'", label),
+            Pkcs11Key::Id(id) => write!(f, "ID {}", id.iter().map(|byte| format!("{:02x}", byte)).collect::<String>()),
+        }
+    }
+}
+
+/// The types of key a `Pkcs11Signer` signs with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pkcs11KeyType {
+    Rsa,
+    Ec(EcCurve),
+}
+
+/// A `Signer` that signs tokens with a private key held by a PKCS#11 module, which never leaves the module
+///
+/// RSA keys sign `RS256`, `RS384`, `RS512`, `PS256`, `PS384` and `PS512` tokens, hashed by the module.
+/// EC keys sign the `ES*` token of their curve, hashed in process and signed with `CKM_ECDSA`.
+/// ECDSA signatures are returned in the fixed width `R || S` encoding JWS requires, even by modules that return DER.
+///
+/// Signing requests share one session and are signed one at a time.
+pub struct Pkcs11Signer {
+    session: Mutex<Session>,
+    selector: Pkcs11Key,
+    private_key: CK_OBJECT_HANDLE,
+    key_type: Pkcs11KeyType,
+    kid: Option<String>,
+}
+
+impl Pkcs11Signer {
+
+    /// Logs in to a token and finds a private key on it
+    ///
+    /// # Arguments
+    /// * `module` - The module the token is in
+    /// * `token_label` - The label of the token
+    /// * `pin` - The user PIN of the token
+    /// * `key` - The label or ID of the private key
+    ///
+    /// # Returns
+    /// * `Result<Pkcs11Signer, Pkcs11Error>` - The signer, or an error if the token or key cannot be found, or the key is not an RSA or EC key
+    pub fn open(module: &Pkcs11Module, token_label: &str, pin: &str, key: Pkcs11Key) -> Result<Self, Pkcs11Error> {
+        let slot = module.find_slot(token_label)?;
+        let session = Session::open(module, slot)?;
+        session.login(pin)?;
+
+        let private_key = session.find_key(ffi::CKO_PRIVATE_KEY, &key)?;
+        let key_type = match ulong_attribute(&session.attribute(private_key, ffi::CKA_KEY_TYPE)?) {
+            Some(ffi::CKK_RSA) => Pkcs11KeyType::Rsa,
+            Some(ffi::CKK_EC) => {
+                let params = session.attribute(private_key, ffi::CKA_EC_PARAMS)?;
+                let oid = ObjectIdentifier::from_der(&params).map_err(|_| Pkcs11Error::UnsupportedKey(format!("The key with {} has EC parameters that are not a named curve", key)))?;
+                let curve = EcCurve::from_oid(oid).ok_or_else(|| Pkcs11Error::UnsupportedKey(format!("The key with {} is on the unsupported curve {}", key, oid)))?;
+                Pkcs11KeyType::Ec(curve)
+            }
+            _ => return Err(Pkcs11Error::UnsupportedKey(format!("The key with {} is not an RSA or EC key", key))),
+        };
+
+        Ok(Pkcs11Signer {
+            session: Mutex::new(session),
+            selector: key,
+            private_key,
+            key_type,
+            kid: None,
+        })
+    }
+
+    /// Sets the key ID, put in the `kid` of tokens whose header has none
+    pub fn with_kid(mut self, kid: impl Into<String>) -> Self {
+        self.kid = Some(kid.into());
+        self
+    }
+
+    /// Reads the public key with the same label or ID as the private key, to verify the tokens it signs
+    ///
+    /// # Returns
+    /// * `Result<DecodingKey, Pkcs11Error>` - The public key, or an error if there is none on the token
+    pub fn verifying_key(&self) -> Result<DecodingKey, Pkcs11Error> {
+        let session = self.session.lock().unwrap_or_else(|err| err.into_inner());
+        let public_key = session.find_key(ffi::CKO_PUBLIC_KEY, &self.selector)?;
+        let invalid = |message: String| Pkcs11Error::UnsupportedKey(format!("The public key with {} cannot be read: {}", self.selector, message));
+
+        let kind = match self.key_type {
+            Pkcs11KeyType::Rsa => {
+                let modulus = BigUint::from_bytes_be(&session.attribute(public_key, ffi::CKA_MODULUS)?);
+                let exponent = BigUint::from_bytes_be(&session.attribute(public_key, ffi::CKA_PUBLIC_EXPONENT)?);
+                DecodingKeyKind::Rsa(RsaPublicKey::new(modulus, exponent).map_err(|err| invalid(err.to_string()))?)
+            }
+            Pkcs11KeyType::Ec(curve) => {
+                let point = session.attribute(public_key, ffi::CKA_EC_POINT)?;
+                let point = unwrap_ec_point(&point, field_length(curve)).map_err(invalid)?;
+                match curve {
+                    EcCurve::P256 => DecodingKeyKind::P256(p256::ecdsa::VerifyingKey::from_sec1_bytes(point).map_err(|err| invalid(err.to_string()))?),
+                    EcCurve::P384 => DecodingKeyKind::P384(p384::ecdsa::VerifyingKey::from_sec1_bytes(point).map_err(|err| invalid(err.to_string()))?),
+                    EcCurve::P521 => DecodingKeyKind::P521(p521::ecdsa::VerifyingKey::from_sec1_bytes(point).map_err(|err| invalid(err.to_string()))?),
+                    EcCurve::Secp256k1 => DecodingKeyKind::Secp256k1(k256::ecdsa::VerifyingKey::from_sec1_bytes(point).map_err(|err| invalid(err.to_string()))?),
+                }
+            }
+        };
+        DecodingKey::checked(kind, &KeyPolicy::default()).map_err(|err| Pkcs11Error::UnsupportedKey(err.to_string()))
+    }
+}
+
+impl Signer for Pkcs11Signer {
+    fn sign(&self, signing_input: &[u8], algorithm: &Algorithm) -> Result<Vec<u8>, SigningError> {
+        // Pick the mechanism, RSA mechanisms hash the input themselves and `CKM_ECDSA` signs a digest
+        let mut pss_params = None;
+        let (mechanism, data) = match (algorithm, self.key_type) {
+            (Algorithm::RS256, Pkcs11KeyType::Rsa) => (ffi::CKM_SHA256_RSA_PKCS, signing_input.to_vec()),
+            (Algorithm::RS384, Pkcs11KeyType::Rsa) => (ffi::CKM_SHA384_RSA_PKCS, signing_input.to_vec()),
+            (Algorithm::RS512, Pkcs11KeyType::Rsa) => (ffi::CKM_SHA512_RSA_PKCS, signing_input.to_vec()),
+            (Algorithm::PS256, Pkcs11KeyType::Rsa) => {
+                pss_params = Some(PssParams { hash_alg: ffi::CKM_SHA256, mgf: ffi::CKG_MGF1_SHA256, s_len: 32 });
+                (ffi::CKM_SHA256_RSA_PKCS_PSS, signing_input.to_vec())
+            }
+            (Algorithm::PS384, Pkcs11KeyType::Rsa) => {
+                pss_params = Some(PssParams { hash_alg: ffi::CKM_SHA384, mgf: ffi::CKG_MGF1_SHA384, s_len: 48 });
+                (ffi::CKM_SHA384_RSA_PKCS_PSS, signing_input.to_vec())
+            }
+            (Algorithm::PS512, Pkcs11KeyType::Rsa) => {
+                pss_params = Some(PssParams { hash_alg: ffi::CKM_SHA512, mgf: ffi::CKG_MGF1_SHA512, s_len: 64 });
+                (ffi::CKM_SHA512_RSA_PKCS_PSS, signing_input.to_vec())
+            }
+            (Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512, _) => {
+                return Err(SigningError::InvalidKey(format!("{} requires an RSA key", algorithm)));
+            }
+            (Algorithm::ES256 | Algorithm::ES384 | Algorithm::ES512 | Algorithm::ES256K, Pkcs11KeyType::Ec(curve)) => {
+                check_curve(curve, algorithm).map_err(|err| SigningError::InvalidKey(err.to_string()))?;
+                let digest = match algorithm {
+                    Algorithm::ES384 => Sha384::digest(signing_input).to_vec(),
+                    Algorithm::ES512 => Sha512::digest(signing_input).to_vec(),
+                    _ => Sha256::digest(signing_input).to_vec(),
+                };
+                (ffi::CKM_ECDSA, digest)
+            }
+            (Algorithm::ES256 | Algorithm::ES384 | Algorithm::ES512 | Algorithm::ES256K, _) => {
+                return Err(SigningError::InvalidKey(format!("{} requires an EC key", algorithm)));
+            }
+            _ => return Err(SigningError::InvalidAlgorithm(algorithm.to_string())),
+        };
+
+        let mechanism = match &mut pss_params {
+            Some(params) => Mechanism {
+                mechanism,
+                parameter: params as *mut PssParams as *mut c_void,
+                parameter_len: size_of::<PssParams>() as CK_ULONG,
+            },
+            None => Mechanism { mechanism, parameter: ptr::null_mut(), parameter_len: 0 },
+        };
+
+        let session = self.session.lock().unwrap_or_else(|err| err.into_inner());
+        let signature = session.sign(&mechanism, self.private_key, &data).map_err(|err| SigningError::Signer(err.to_string()))?;
+        match self.key_type {
+            Pkcs11KeyType::Ec(curve) => jose_signature(&signature, field_length(curve)).map_err(SigningError::Signer),
+            Pkcs11KeyType::Rsa => Ok(signature),
+        }
+    }
+
+    fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+}
+
+/// An open session with a token, closed when dropped
+struct Session {
+    module: Pkcs11Module,
+    handle: CK_SESSION_HANDLE,
+}
+
+impl Session {
+
+    fn open(module: &Pkcs11Module, slot: CK_SLOT_ID) -> Result<Self, Pkcs11Error> {
+        let open_session = module.functions().open_session.ok_or_else(|| missing("C_OpenSession"))?;
+        let mut handle: CK_SESSION_HANDLE = 0;
+        check("C_OpenSession", unsafe { open_session(slot, ffi::CKF_SERIAL_SESSION, ptr::null_mut(), ptr::null_mut(), &mut handle) })?;
+        Ok(Session { module: module.clone(), handle })
+    }
+
+    fn login(&self, pin: &str) -> Result<(), Pkcs11Error> {
+        let login = self.module.functions().login.ok_or_else(|| missing("C_Login"))?;
+        let rv = unsafe { login(self.handle, ffi::CKU_USER, pin.as_ptr(), pin.len() as CK_ULONG) };
+        // Logins are shared by every session of the process, so another session may have logged in already
+        if rv != ffi::CKR_OK && rv != ffi::CKR_USER_ALREADY_LOGGED_IN {
+            return Err(call_error("C_Login", rv));
+        }
+        Ok(())
+    }
+
+    /// Finds the one key of a class with a label or ID
+    fn find_key(&self, class: CK_ULONG, key: &Pkcs11Key) -> Result<CK_OBJECT_HANDLE, Pkcs11Error> {
+        let functions = self.module.functions();
+        let find_objects_init = functions.find_objects_init.ok_or_else(|| missing("C_FindObjectsInit"))?;
+        let find_objects = functions.find_objects.ok_or_else(|| missing("C_FindObjects"))?;
+        let find_objects_final = functions.find_objects_final.ok_or_else(|| missing("C_FindObjectsFinal"))?;
+
+        let mut class = class;
+        let (selector_type, selector) = match key {
+            Pkcs11Key::Label(label) => (ffi::CKA_LABEL, label.as_bytes()),
+            Pkcs11Key::Id(id) => (ffi::CKA_ID, id.as_slice()),
+        };
+        let mut template = [
+            Attribute { attribute_type: ffi::CKA_CLASS, value: &mut class as *mut CK_ULONG as *mut c_void, value_len: size_of::<CK_ULONG>() as CK_ULONG },
+            Attribute { attribute_type: selector_type, value: selector.as_ptr() as *mut c_void, value_len: selector.len() as CK_ULONG },
+        ];
+
+        check("C_FindObjectsInit", unsafe { find_objects_init(self.handle, template.as_mut_ptr(), template.len() as CK_ULONG) })?;
+        // Ask for two objects to tell whether the key is ambiguous
+        let mut objects: [CK_OBJECT_HANDLE; 2] = [0; 2];
+        let mut count: CK_ULONG = 0;
+        let found = check("C_FindObjects", unsafe { find_objects(self.handle, objects.as_mut_ptr(), objects.len() as CK_ULONG, &mut count) });
+        let finished = check("C_FindObjectsFinal", unsafe { find_objects_final(self.handle) });
+        found.and(finished)?;
+
+        let kind = if class == ffi::CKO_PRIVATE_KEY { "private" } else { "public" };
+        match count {
+            0 => Err(Pkcs11Error::KeyNotFound(format!("No {} key with {}", kind, key))),
+            1 => Ok(objects[0]),
+            _ => Err(Pkcs11Error::KeyNotFound(format!("Several {} keys with {}, use a label or ID that is unique", kind, key))),
+        }
+    }
+
+    /// Reads the value of an attribute of an object
+    fn attribute(&self, object: CK_OBJECT_HANDLE, attribute_type: CK_ULONG) -> Result<Vec<u8>, Pkcs11Error> {
+        let get_attribute_value = self.module.functions().get_attribute_value.ok_or_else(|| missing("C_GetAttributeValue"))?;
+
+        // The first call returns the length of the value, the second the value
+        let mut template = Attribute { attribute_type, value: ptr::null_mut(), value_len: 0 };
+        check("C_GetAttributeValue", unsafe { get_attribute_value(self.handle, object, &mut template, 1) })?;
+        if template.value_len == ffi::CK_UNAVAILABLE_INFORMATION {
+            return Err(Pkcs11Error::UnsupportedKey(format!("The key does not have attribute 0x{:x}", attribute_type)));
+        }
+        let mut value = vec![0u8; template.value_len as usize];
+        template.value = value.as_mut_ptr() as *mut c_void;
+        check("C_GetAttributeValue", unsafe { get_attribute_value(self.handle, object, &mut template, 1) })?;
+        value.truncate(template.value_len as usize);
+        Ok(value)
+    }
+
+    fn sign(&self, mechanism: &Mechanism, key: CK_OBJECT_HANDLE, data: &[u8]) -> Result<Vec<u8>, Pkcs11Error> {
+        let functions = self.module.functions();
+        let sign_init = functions.sign_init.ok_or_else(|| missing("C_SignInit"))?;
+        let sign = functions.sign.ok_or_else(|| missing("C_Sign"))?;
+
+        check("C_SignInit", unsafe { sign_init(self.handle, mechanism, key) })?;
+        // The first call returns the length of the signature and keeps the operation active, the second signs
+        let mut length: CK_ULONG = 0;
+        check("C_Sign", unsafe { sign(self.handle, data.as_ptr(), data.len() as CK_ULONG, ptr::null_mut(), &mut length) })?;
+        let mut signature = vec![0u8; length as usize];
+        check("C_Sign", unsafe { sign(self.handle, data.as_ptr(), data.len() as CK_ULONG, signature.as_mut_ptr(), &mut length) })?;
+        signature.truncate(length as usize);
+        Ok(signature)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if let Some(close_session) = self.module.functions().close_session {
+            unsafe { close_session(self.handle) };
+        }
+    }
+}
+
+/// The length in bytes of a coordinate, and of each half of an ECDSA signature, on a curve
+fn field_length(curve: EcCurve) -> usize {
+    match curve {
+        EcCurve::P256 | EcCurve::Secp256k1 => 32,
+        EcCurve::P384 => 48,
+        EcCurve::P521 => 66,
+    }
+}
+
+/// Converts an ECDSA signature to the fixed width `R || S` encoding JWS requires
+///
+/// PKCS#11 specifies `R || S`, but some modules return the DER `SEQUENCE` of two `INTEGER`s used by X.509 instead.
+pub(crate) fn jose_signature(signature: &[u8], field_length: usize) -> Result<Vec<u8>, String> {
+    if signature.len() == 2 * field_length {
+        return Ok(signature.to_vec());
+    }
+
+    let invalid = || format!("The module returned a {} byte ECDSA signature that is neither R || S nor DER", signature.len());
+    let (sequence, rest) = der_element(signature, 0x30).ok_or_else(invalid)?;
+    let (r, sequence) = der_element(sequence, 0x02).ok_or_else(invalid)?;
+    let (s, sequence) = der_element(sequence, 0x02).ok_or_else(invalid)?;
+    if !rest.is_empty() || !sequence.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut jose = vec![0u8; 2 * field_length];
+    let (r_half, s_half) = jose.split_at_mut(field_length);
+    for (integer, half) in [(r, r_half), (s, s_half)] {
+        // DER integers have a leading zero when the high bit is set, and no padding otherwise
+        let integer = &integer[integer.iter().take_while(|byte| **byte == 0).count()..];
+        if integer.len() > field_length {
+            return Err(invalid());
+        }
+        half[field_length - integer.len()..].copy_from_slice(integer);
+    }
+    Ok(jose)
+}
+
+/// Reads a DER element with a tag, returning its contents and the bytes after it
+fn der_element(der: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (&found, der) = der.split_first()?;
+    if found != tag {
+        return None;
+    }
+    let (&length, der) = der.split_first()?;
+    let (length, der) = match length {
+        0x00..=0x7f => (length as usize, der),
+        0x81 => (*der.first()? as usize, &der[1..]),
+        0x82 => (u16::from_be_bytes([*der.first()?, *der.get(1)?]) as usize, &der[2..]),
+        _ => return None,
+    };
+    (der.len() >= length).then(|| der.split_at(length))
+}
+
+/// Reads an uncompressed EC point, which PKCS#11 wraps in a DER `OCTET STRING` though some modules return it bare
+fn unwrap_ec_point(point: &[u8], field_length: usize) -> Result<&[u8], String> {
+    if point.len() == 1 + 2 * field_length {
+        return Ok(point);
+    }
+    der_element(point, 0x04).filter(|(_, rest)| rest.is_empty()).map(|(point, _)| point).ok_or_else(|| "The EC point is not an OCTET STRING".to_string())
+}
+
+/// Reads a `CK_ULONG` attribute value
+fn ulong_attribute(value: &[u8]) -> Option<CK_ULONG> {
+    Some(CK_ULONG::from_ne_bytes(value.try_into().ok()?))
+}
+
+fn check(function: &'static str, rv: CK_RV) -> Result<(), Pkcs11Error> {
+    match rv {
+        ffi::CKR_OK => Ok(()),
+        rv => Err(call_error(function, rv)),
+    }
+}
+
+#[allow(clippy::unnecessary_cast)] // `CK_RV` is 32 bits on Windows
+fn call_error(function: &'static str, rv: CK_RV) -> Pkcs11Error {
+    Pkcs11Error::Call(function, rv as u64)
+}
+
+fn missing(function: &str) -> Pkcs11Error {
+    Pkcs11Error::Module(format!("The module does not provide {}", function))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Error type returned when a PKCS#11 module, token or key cannot be used
+///
+/// # Variants
+/// * `Module` - The module could not be loaded, or is not a PKCS#11 module
+/// * `Call` - A PKCS#11 function failed, with its name and `CKR_` return value
+/// * `TokenNotFound` - No token has the label
+/// * `KeyNotFound` - No key, or more than one key, has the label or ID
+/// * `UnsupportedKey` - The key is not an RSA or EC key on a supported curve, or cannot be read
+pub enum Pkcs11Error {
+    /// The module could not be loaded, or is not a PKCS#11 module
+    Module(String),
+    /// A PKCS#11 function failed, with its name and `CKR_` return value
+    Call(&'static str, u64),
+    /// No token has the label
+    TokenNotFound(String),
+    /// No key, or more than one key, has the label or ID
+    KeyNotFound(String),
+    /// The key is not an RSA or EC key on a supported curve, or cannot be read
+    UnsupportedKey(String),
+}
+
+impl Display for Pkcs11Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pkcs11Error::Module(message) => write!(f, "{}", message),
+            Pkcs11Error::Call(function, rv) => write!(f, "{} failed with CKR 0x{:08x}", function, rv),
+            Pkcs11Error::TokenNotFound(label) => write!(f, "No token has the label '{}'", label),
+            Pkcs11Error::KeyNotFound(message) => write!(f, "{}", message),
+            Pkcs11Error::UnsupportedKey(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// The parts of the PKCS#11 2.40 C interface the signer uses, from `pkcs11t.h` and `pkcs11f.h`
+///
+/// Structures are packed to one byte on Windows, as the standard requires there.
+#[allow(non_camel_case_types)]
+mod ffi {
+    use std::ffi::c_void;
+    use std::os::raw::c_ulong;
+
+    pub type CK_ULONG = c_ulong;
+    pub type CK_RV = CK_ULONG;
+    pub type CK_SLOT_ID = CK_ULONG;
+    pub type CK_SESSION_HANDLE = CK_ULONG;
+    pub type CK_OBJECT_HANDLE = CK_ULONG;
+
+    pub const CK_TRUE: u8 = 1;
+    pub const CK_UNAVAILABLE_INFORMATION: CK_ULONG = !0;
+
+    pub const CKR_OK: CK_RV = 0x000;
+    pub const CKR_USER_ALREADY_LOGGED_IN: CK_RV = 0x100;
+    pub const CKR_CRYPTOKI_ALREADY_INITIALIZED: CK_RV = 0x191;
+
+    pub const CKF_OS_LOCKING_OK: CK_ULONG = 0x2;
+    pub const CKF_SERIAL_SESSION: CK_ULONG = 0x4;
+    pub const CKU_USER: CK_ULONG = 1;
+
+    pub const CKO_PUBLIC_KEY: CK_ULONG = 2;
+    pub const CKO_PRIVATE_KEY: CK_ULONG = 3;
+    pub const CKK_RSA: CK_ULONG = 0x00;
+    pub const CKK_EC: CK_ULONG = 0x03;
+
+    pub const CKA_CLASS: CK_ULONG = 0x000;
+    pub const CKA_LABEL: CK_ULONG = 0x003;
+    pub const CKA_KEY_TYPE: CK_ULONG = 0x100;
+    pub const CKA_ID: CK_ULONG = 0x102;
+    pub const CKA_MODULUS: CK_ULONG = 0x120;
+    pub const CKA_PUBLIC_EXPONENT: CK_ULONG = 0x122;
+    pub const CKA_EC_PARAMS: CK_ULONG = 0x180;
+    pub const CKA_EC_POINT: CK_ULONG = 0x181;
+
+    pub const CKM_SHA256_RSA_PKCS: CK_ULONG = 0x040;
+    pub const CKM_SHA384_RSA_PKCS: CK_ULONG = 0x041;
+    pub const CKM_SHA512_RSA_PKCS: CK_ULONG = 0x042;
+    pub const CKM_SHA256_RSA_PKCS_PSS: CK_ULONG = 0x043;
+    pub const CKM_SHA384_RSA_PKCS_PSS: CK_ULONG = 0x044;
+    pub const CKM_SHA512_RSA_PKCS_PSS: CK_ULONG = 0x045;
+    pub const CKM_SHA256: CK_ULONG = 0x250;
+    pub const CKM_SHA384: CK_ULONG = 0x260;
+    pub const CKM_SHA512: CK_ULONG = 0x270;
+    pub const CKM_ECDSA: CK_ULONG = 0x1041;
+    pub const CKG_MGF1_SHA256: CK_ULONG = 0x2;
+    pub const CKG_MGF1_SHA384: CK_ULONG = 0x3;
+    pub const CKG_MGF1_SHA512: CK_ULONG = 0x4;
+
+    pub type GetFunctionList = unsafe extern "C" fn(*mut *mut FunctionList) -> CK_RV;
+
+    #[cfg_attr(windows, repr(C, packed(1)))]
+    #[cfg_attr(not(windows), repr(C))]
+    pub struct InitializeArgs {
+        pub create_mutex: *mut c_void,
+        pub destroy_mutex: *mut c_void,
+        pub lock_mutex: *mut c_void,
+        pub unlock_mutex: *mut c_void,
+        pub flags: CK_ULONG,
+        pub reserved: *mut c_void,
+    }
+
+    #[cfg_attr(windows, repr(C, packed(1)))]
+    #[cfg_attr(not(windows), repr(C))]
+    pub struct Version {
+        pub major: u8,
+        pub minor: u8,
+    }
+
+    #[cfg_attr(windows, repr(C, packed(1)))]
+    #[cfg_attr(not(windows), repr(C))]
+    pub struct TokenInfo {
+        pub label: [u8; 32],
+        pub manufacturer_id: [u8; 32],
+        pub model: [u8; 16],
+        pub serial_number: [u8; 16],
+        pub flags: CK_ULONG,
+        pub max_session_count: CK_ULONG,
+        pub session_count: CK_ULONG,
+        pub max_rw_session_count: CK_ULONG,
+        pub rw_session_count: CK_ULONG,
+        pub max_pin_len: CK_ULONG,
+        pub min_pin_len: CK_ULONG,
+        pub total_public_memory: CK_ULONG,
+        pub free_public_memory: CK_ULONG,
+        pub total_private_memory: CK_ULONG,
+        pub free_private_memory: CK_ULONG,
+        pub hardware_version: Version,
+        pub firmware_version: Version,
+        pub utc_time: [u8; 16],
+    }
+
+    impl Default for TokenInfo {
+        fn default() -> Self {
+            TokenInfo {
+                label: [0; 32],
+                manufacturer_id: [0; 32],
+                model: [0; 16],
+                serial_number: [0; 16],
+                flags: 0,
+                max_session_count: 0,
+                session_count: 0,
+                max_rw_session_count: 0,
+                rw_session_count: 0,
+                max_pin_len: 0,
+                min_pin_len: 0,
+                total_public_memory: 0,
+                free_public_memory: 0,
+                total_private_memory: 0,
+                free_private_memory: 0,
+                hardware_version: Version { major: 0, minor: 0 },
+                firmware_version: Version { major: 0, minor: 0 },
+                utc_time: [0; 16],
+            }
+        }
+    }
+
+    #[cfg_attr(windows, repr(C, packed(1)))]
+    #[cfg_attr(not(windows), repr(C))]
+    pub struct Attribute {
+        pub attribute_type: CK_ULONG,
+        pub value: *mut c_void,
+        pub value_len: CK_ULONG,
+    }
+
+    #[cfg_attr(windows, repr(C, packed(1)))]
+    #[cfg_attr(not(windows), repr(C))]
+    pub struct Mechanism {
+        pub mechanism: CK_ULONG,
+        pub parameter: *mut c_void,
+        pub parameter_len: CK_ULONG,
+    }
+
+    #[cfg_attr(windows, repr(C, packed(1)))]
+    #[cfg_attr(not(windows), repr(C))]
+    pub struct PssParams {
+        pub hash_alg: CK_ULONG,
+        pub mgf: CK_ULONG,
+        pub s_len: CK_ULONG,
+    }
+
+    /// A function the signer does not call, kept to preserve the layout of the list
+    type Unused = Option<unsafe extern "C" fn()>;
+
+    /// `CK_FUNCTION_LIST`, up to `C_Sign`, the last function the signer calls
+    #[cfg_attr(windows, repr(C, packed(1)))]
+    #[cfg_attr(not(windows), repr(C))]
+    pub struct FunctionList {
+        pub version: Version,
+        pub initialize: Option<unsafe extern "C" fn(*mut c_void) -> CK_RV>,
+        pub finalize: Option<unsafe extern "C" fn(*mut c_void) -> CK_RV>,
+        get_info: Unused,
+        get_function_list: Unused,
+        pub get_slot_list: Option<unsafe extern "C" fn(u8, *mut CK_SLOT_ID, *mut CK_ULONG) -> CK_RV>,
+        get_slot_info: Unused,
+        pub get_token_info: Option<unsafe extern "C" fn(CK_SLOT_ID, *mut TokenInfo) -> CK_RV>,
+        get_mechanism_list: Unused,
+        get_mechanism_info: Unused,
+        init_token: Unused,
+        init_pin: Unused,
+        set_pin: Unused,
+        pub open_session: Option<unsafe extern "C" fn(CK_SLOT_ID, CK_ULONG, *mut c_void, *mut c_void, *mut CK_SESSION_HANDLE) -> CK_RV>,
+        pub close_session: Option<unsafe extern "C" fn(CK_SESSION_HANDLE) -> CK_RV>,
+        close_all_sessions: Unused,
+        get_session_info: Unused,
+        get_operation_state: Unused,
+        set_operation_state: Unused,
+        pub login: Option<unsafe extern "C" fn(CK_SESSION_HANDLE, CK_ULONG, *const u8, CK_ULONG) -> CK_RV>,
+        logout: Unused,
+        create_object: Unused,
+        copy_object: Unused,
+        destroy_object: Unused,
+        get_object_size: Unused,
+        pub get_attribute_value: Option<unsafe extern "C" fn(CK_SESSION_HANDLE, CK_OBJECT_HANDLE, *mut Attribute, CK_ULONG) -> CK_RV>,
+        set_attribute_value: Unused,
+        pub find_objects_init: Option<unsafe extern "C" fn(CK_SESSION_HANDLE, *mut Attribute, CK_ULONG) -> CK_RV>,
+        pub find_objects: Option<unsafe extern "C" fn(CK_SESSION_HANDLE, *mut CK_OBJECT_HANDLE, CK_ULONG, *mut CK_ULONG) -> CK_RV>,
+        pub find_objects_final: Option<unsafe extern "C" fn(CK_SESSION_HANDLE) -> CK_RV>,
+        encrypt_init: Unused,
+        encrypt: Unused,
+        encrypt_update: Unused,
+        encrypt_final: Unused,
+        decrypt_init: Unused,
+        decrypt: Unused,
+        decrypt_update: Unused,
+        decrypt_final: Unused,
+        digest_init: Unused,
+        digest: Unused,
+        digest_update: Unused,
+        digest_key: Unused,
+        digest_final: Unused,
+        pub sign_init: Option<unsafe extern "C" fn(CK_SESSION_HANDLE, *const Mechanism, CK_OBJECT_HANDLE) -> CK_RV>,
+        pub sign: Option<unsafe extern "C" fn(CK_SESSION_HANDLE, *const u8, CK_ULONG, *mut u8, *mut CK_ULONG) -> CK_RV>,
+    }
+}
@@ -0,0 +1,105 @@
+use std::future::Future;
+
+use crate::keys::encoding_key::EncodingKey;
+use crate::model::header::Algorithm;
+
+use super::{ecdsa, eddsa, hmac, rsa, SigningError};
+
+/// Signs the signing input of a token, the base64 encoded header and claims joined with a dot
+///
+/// Implement this to sign with a key that never leaves its backend, e.g. a hardware security module or a cloud key service,
+/// and pass the signer to `sign_with_key` in place of an `EncodingKey`.
+/// `EncodingKey` implements it for the built-in RSA, EC, EdDSA and HMAC keys.
+///
+/// # Example
+/// ```rust
+/// use super_simple_jwt::{sign_with_key, Algorithm, EncodingKey, Header, Signer, SignTokenError};
+/// # #[derive(serde::Serialize, serde::Deserialize, Clone)]
+/// # struct Claims { exp: usize, sub: String }
+///
+/// struct RemoteSigner {
+///     // Stands in for a client of a key service
+///     key: EncodingKey,
+/// }
+///
+/// impl Signer for RemoteSigner {
+///     fn sign(&self, signing_input: &[u8], algorithm: &Algorithm) -> Result<Vec<u8>, SignTokenError> {
+///         if *algorithm != Algorithm::ES256 {
+///             return Err(SignTokenError::InvalidAlgorithm(algorithm.to_string()));
+///         }
+///         self.key.sign(signing_input, algorithm)
+///     }
+///
+///     fn kid(&self) -> Option<&str> {
+///         Some("remote-key-1")
+///     }
+/// }
+///
+/// let signer = RemoteSigner { key: EncodingKey::generate(&Algorithm::ES256).unwrap() };
+/// let claims = Claims { exp: 1700000000, sub: "user".to_string() };
+/// let token = sign_with_key(&Header::new(Algorithm::ES256), &claims, &signer).unwrap();
+/// ```
+pub trait Signer {
+    /// Signs the signing input with the algorithm of the token
+    ///
+    /// # Arguments
+    /// * `signing_input` - The base64 encoded header and claims joined with a dot
+    /// * `algorithm` - The algorithm in the token header, which the signer should reject if its key cannot be used with it
+    ///
+    /// # Returns
+    /// * `Result<Vec<u8>, SigningError>` - The bytes of the signature, in the encoding RFC 7518 requires for the algorithm, e.g. `R || S` for ECDSA
+    fn sign(&self, signing_input: &[u8], algorithm: &Algorithm) -> Result<Vec<u8>, SigningError>;
+
+    /// The key ID of the key, put in the `kid` of tokens whose header has none
+    fn kid(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// The asynchronous form of `Signer`, for backends that sign over the network, used by `sign_async`
+///
+/// Every `Signer` that is `Sync` is also an `AsyncSigner`, so the built-in keys can be used with `sign_async` as well.
+pub trait AsyncSigner {
+    /// Signs the signing input with the algorithm of the token
+    ///
+    /// # Arguments
+    /// * `signing_input` - The base64 encoded header and claims joined with a dot
+    /// * `algorithm` - The algorithm in the token header, which the signer should reject if its key cannot be used with it
+    ///
+    /// # Returns
+    /// * `Result<Vec<u8>, SigningError>` - The bytes of the signature, in the encoding RFC 7518 requires for the algorithm
+    fn sign(&self, signing_input: &[u8], algorithm: &Algorithm) -> impl Future<Output = Result<Vec<u8>, SigningError>> + Send;
+
+    /// The key ID of the key, put in the `kid` of tokens whose header has none
+    fn kid(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl<S: Signer + Sync + ?Sized> AsyncSigner for S {
+    fn sign(&self, signing_input: &[u8], algorithm: &Algorithm) -> impl Future<Output = Result<Vec<u8>, SigningError>> + Send {
+        std::future::ready(Signer::sign(self, signing_input, algorithm))
+    }
+
+    fn kid(&self) -> Option<&str> {
+        Signer::kid(self)
+    }
+}
+
+impl Signer for EncodingKey {
+    /// Signs with the built-in key, which must be of the type the algorithm requires
+    fn sign(&self, signing_input: &[u8], algorithm: &Algorithm) -> Result<Vec<u8>, SigningError> {
+        match algorithm {
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512 => rsa::signature(signing_input, self, algorithm),
+            Algorithm::ES256 | Algorithm::ES384 | Algorithm::ES512 | Algorithm::ES256K => ecdsa::signature(signing_input, self, algorithm),
+            Algorithm::EdDSA => eddsa::signature(signing_input, self, algorithm),
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => hmac::signature(signing_input, self, algorithm),
+            // Unsecured tokens can only be created through `dangerous_encode_unsecured`
+            Algorithm::None => Err(SigningError::InvalidAlgorithm(algorithm.to_string())),
+        }
+    }
+
+    fn kid(&self) -> Option<&str> {
+        EncodingKey::kid(self)
+    }
+}
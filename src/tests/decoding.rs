@@ -18,6 +18,45 @@ mod header {
         // Check if the decoded header is equal to the expected header
         assert_eq!(decoded, expected, "Header decoding failed. Expected: {:?}, got: {:?}", expected, decoded);
     }
+
+    #[test]
+    fn test_header_decode_base64url() {
+        // Tokens use unpadded base64url, so `_` and `-` must decode, {"alg":"HS256","kid":"??>"}
+        let decoded = decode("eyJhbGciOiJIUzI1NiIsImtpZCI6Ij8_PiJ9").unwrap();
+        assert_eq!(decoded.kid, Some("??>".to_string()));
+
+        // Standard base64 is not what tokens use
+        assert!(decode("eyJhbGciOiJIUzI1NiIsImtpZCI6Ij8/PiJ9").is_err());
+    }
+
+    #[test]
+    fn test_header_decode_registered_parameters() {
+        use base64::Engine;
+        use crate::model::jwk::{Jwk, JwkKey};
+
+        let json = r#"{"alg":"ES256","typ":"at+jwt","cty":"JWT","kid":"key-1","jku":"https://example.com/jwks.json","jwk":{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"},"x5u":"https://example.com/chain.pem","x5t":"dGh1bWI","crit":["exp"]}"#;
+        let decoded = decode(&crate::encoding::ENCODING_ENGINE.encode(json)).unwrap();
+
+        let expected = Header {
+            typ: Some("at+jwt".to_string()),
+            cty: Some("JWT".to_string()),
+            kid: Some("key-1".to_string()),
+            jku: Some("https://example.com/jwks.json".to_string()),
+            jwk: Some(Jwk::new(JwkKey::Okp { crv: "Ed25519".to_string(), x: "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo".to_string(), d: None })),
+            x5u: Some("https://example.com/chain.pem".to_string()),
+            x5t: Some("dGh1bWI".to_string()),
+            crit: Some(vec!["exp".to_string()]),
+            ..Header::new(Algorithm::ES256)
+        };
+        assert_eq!(decoded, expected);
+
+        // Parameters that are left out stay unset, including `typ`
+        let decoded = decode(&crate::encoding::ENCODING_ENGINE.encode(r#"{"alg":"HS256"}"#)).unwrap();
+        assert_eq!(decoded, Header { typ: None, ..Header::new(Algorithm::HS256) });
+
+        // A parameter with the wrong type is an error rather than being dropped
+        assert!(decode(&crate::encoding::ENCODING_ENGINE.encode(r#"{"alg":"HS256","typ":1}"#)).is_err());
+    }
 }
 
 #[cfg(test)]
@@ -50,4 +89,19 @@ mod claims {
         // Check if the decoded claims are equal to the expected claims
         assert_eq!(decoded, expected, "Claims decoding failed. Expected: {:?}, got: {:?}", expected, decoded);
     }
+
+    #[test]
+    fn test_claims_decode_base64url() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+        struct Claims {
+            exp: usize,
+            sub: String,
+        }
+
+        // The subjects encode to `_` and `-`, which standard base64 rejects
+        let decoded: Claims = decode("eyJleHAiOjEwMDAwMDAwMDAwLCJzdWIiOiI_Pz4ifQ").unwrap();
+        assert_eq!(decoded, Claims { exp: 10000000000, sub: "??>".to_string() });
+        let decoded: Claims = decode("eyJleHAiOjEwMDAwMDAwMDAwLCJzdWIiOiJ-fn4ifQ").unwrap();
+        assert_eq!(decoded, Claims { exp: 10000000000, sub: "~~~".to_string() });
+    }
 }
\ No newline at end of file
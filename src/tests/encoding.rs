@@ -23,6 +23,39 @@ mod header {
             assert!(false, "Header encoding failed. Expected: {} or {}, got: {}", expected_header1, expected_header2, encoded_header);
         }
     }
+
+    #[test]
+    pub fn test_header_encode_registered_parameters() {
+        use base64::Engine;
+
+        let header = Header {
+            typ: Some("at+jwt".to_string()),
+            cty: Some("JWT".to_string()),
+            kid: Some("key-1".to_string()),
+            x5u: Some("https://example.com/chain.pem".to_string()),
+            ..Header::new(Algorithm::PS256)
+        };
+        let encoded_header = encode(&header).unwrap();
+        let json = String::from_utf8(crate::decoding::DECODING_ENGINE.decode(&encoded_header).unwrap()).unwrap();
+        assert_eq!(json, r#"{"alg":"PS256","cty":"JWT","kid":"key-1","typ":"at+jwt","x5u":"https://example.com/chain.pem"}"#, "Only the parameters that are set should be written");
+        assert_eq!(crate::decode_header(&encoded_header).unwrap(), header);
+
+        // `typ` can be left out
+        let encoded_header = encode(&Header { typ: None, ..Header::new(Algorithm::PS256) }).unwrap();
+        assert_eq!(String::from_utf8(crate::decoding::DECODING_ENGINE.decode(&encoded_header).unwrap()).unwrap(), r#"{"alg":"PS256"}"#);
+    }
+
+    #[test]
+    pub fn test_header_encode_extra_parameters() {
+        use base64::Engine;
+
+        // Extra parameters are written alongside the registered ones and read back exactly
+        let json = r#"{"alg":"HS256","kid":"key-1","tenant":"acme","typ":"JWT","x-env":{"big":12345678901234,"list":[1,"two",null]}}"#;
+        let decoded = crate::decode_header(&crate::encoding::ENCODING_ENGINE.encode(json)).unwrap();
+        assert_eq!(decoded.extra.keys().collect::<Vec<_>>(), vec!["tenant", "x-env"], "Registered parameters should not be extra");
+        let encoded_header = encode(&decoded).unwrap();
+        assert_eq!(String::from_utf8(crate::decoding::DECODING_ENGINE.decode(&encoded_header).unwrap()).unwrap(), json);
+    }
 }
 
 #[cfg(test)]
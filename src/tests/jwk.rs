@@ -0,0 +1,299 @@
+#![allow(unused)]
+use serde::{Deserialize, Serialize};
+
+use crate::{Algorithm, DecodingKey, DecodingKeySet, EncodingKey, Header, Jwk, JwkKey, JwkSet, KeyError, VerifyTokenError};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Claims {
+    exp: usize,
+    sub: String,
+}
+
+/// A P-256 private key as a JWK
+const EC_JWK: &str = r#"{"kty":"EC","crv":"P-256","x":"gv4qSvn4RbNLi7T0a41CWgp35I1ff65BewVNCXRXG9I","y":"wxnxngQNsukYtT7DMbtvw_OKC3U_qidPE4rr7pXSTi0","d":"jpsQnnGQmL-YBIffH1136cLSG7Dbjsg1ePh0l4IJ2NI"}"#;
+
+#[test]
+fn test_jwk_round_trip_successful() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+
+    let keys = [
+        (Algorithm::RS256, EncodingKey::from_rsa_pem(&std::fs::read_to_string("src/tests/test_private.pem").unwrap()).unwrap(), "src/tests/test_public.pem"),
+        (Algorithm::ES256, EncodingKey::from_ec_pem(&std::fs::read_to_string("src/tests/test_es256_private.pem").unwrap()).unwrap(), "src/tests/test_es256_public.pem"),
+        (Algorithm::ES384, EncodingKey::from_ec_pem(&std::fs::read_to_string("src/tests/test_es384_private.pem").unwrap()).unwrap(), "src/tests/test_es384_public.pem"),
+        (Algorithm::ES512, EncodingKey::from_ec_pem(&std::fs::read_to_string("src/tests/test_es512_private.pem").unwrap()).unwrap(), "src/tests/test_es512_public.pem"),
+        (Algorithm::ES256K, EncodingKey::from_ec_pem(&std::fs::read_to_string("src/tests/test_es256k_private.pem").unwrap()).unwrap(), "src/tests/test_es256k_public.pem"),
+        (Algorithm::EdDSA, EncodingKey::from_ed_pem(&std::fs::read_to_string("src/tests/test_ed25519_private.pem").unwrap()).unwrap(), "src/tests/test_ed25519_public.pem"),
+        (Algorithm::EdDSA, EncodingKey::from_ed_pem(&std::fs::read_to_string("src/tests/test_ed448_private.pem").unwrap()).unwrap(), "src/tests/test_ed448_public.pem"),
+    ];
+
+    for (algorithm, encoding_key, public_path) in keys {
+        // The private JWK survives serde and still signs
+        let json = serde_json::to_string(&encoding_key.to_jwk().unwrap()).unwrap();
+        let private_jwk: Jwk = serde_json::from_str(&json).unwrap();
+        assert!(private_jwk.is_private());
+        let signed_token = crate::sign_with_key(&Header::new(algorithm.clone()), &claims, &EncodingKey::from_jwk(&private_jwk).unwrap()).unwrap();
+
+        // The public JWK matches the PEM public key and verifies
+        let public_jwk = encoding_key.to_public_jwk().unwrap();
+        let decoding_key = DecodingKey::from_rsa_pem(&std::fs::read_to_string(public_path).unwrap())
+            .or_else(|_| DecodingKey::from_ec_pem(&std::fs::read_to_string(public_path).unwrap()))
+            .or_else(|_| DecodingKey::from_ed_pem(&std::fs::read_to_string(public_path).unwrap()))
+            .unwrap();
+        assert_eq!(decoding_key.to_jwk(), public_jwk, "{} public JWK should match the PEM key", json);
+        assert_eq!(private_jwk.to_public(), Some(public_jwk.clone()));
+        assert!(!public_jwk.is_private());
+
+        let verified: Claims = crate::verify_with_key(&signed_token, &DecodingKey::from_jwk(&public_jwk).unwrap()).unwrap();
+        assert_eq!(verified, claims, "Claims should survive a {} JWK round trip", algorithm);
+        let verified: Claims = crate::verify_with_key(&signed_token, &DecodingKey::from_jwk(&private_jwk).unwrap()).unwrap();
+        assert_eq!(verified, claims, "A private JWK should also verify");
+    }
+
+    // Shared secrets are `oct` keys
+    let jwk: Jwk = serde_json::from_str(r#"{"kty":"oct","k":"eW91ci0yNTYtYml0LXNlY3JldC1mb3ItdGhlLWp3a3M"}"#).unwrap();
+    assert_eq!(jwk.key, JwkKey::Oct { k: "eW91ci0yNTYtYml0LXNlY3JldC1mb3ItdGhlLWp3a3M".to_string().into() });
+    let signed_token = crate::sign_with_key(&Header::new(Algorithm::HS256), &claims, &EncodingKey::from_jwk(&jwk).unwrap()).unwrap();
+    let verified: Claims = crate::verify(&signed_token, "your-256-bit-secret-for-the-jwks").unwrap();
+    assert_eq!(verified, claims);
+    assert_eq!(EncodingKey::from_secret(b"your-256-bit-secret-for-the-jwks").unwrap().to_jwk().unwrap(), jwk);
+    assert_eq!(jwk.to_public(), None, "Shared secrets have no public form");
+}
+
+#[test]
+fn test_jwk_members_round_trip() {
+    let json = r#"{"kty":"EC","crv":"P-256","x":"f83OJ3D2xF1Bg8vub9tLe1gHMzV76e8Tus9uPHvRVEU","y":"x_FEzRu9m36HLN_tue659LNpXW6pCyStikYjKIWI5a0","kid":"key-1","use":"sig","key_ops":["verify"],"alg":"ES256","x5u":"https://example.com/chain.pem","x5c":["MIIB"],"x5t":"dGh1bWI","x5t#S256":"dGh1bWIyNTY"}"#;
+    let jwk: Jwk = serde_json::from_str(json).unwrap();
+    assert_eq!(jwk.kid.as_deref(), Some("key-1"));
+    assert_eq!(jwk.key_use.as_deref(), Some("sig"));
+    assert_eq!(jwk.key_ops, Some(vec!["verify".to_string()]));
+    assert_eq!(jwk.alg.as_deref(), Some("ES256"));
+    assert_eq!(jwk.x5t_s256.as_deref(), Some("dGh1bWIyNTY"));
+
+    let serialized: serde_json::Value = serde_json::to_value(&jwk).unwrap();
+    assert_eq!(serialized, serde_json::from_str::<serde_json::Value>(json).unwrap(), "Every member should survive a round trip");
+
+    // A private key reads and writes unchanged
+    let jwk: Jwk = serde_json::from_str(EC_JWK).unwrap();
+    assert_eq!(EncodingKey::from_jwk(&jwk).unwrap().to_jwk().unwrap(), jwk);
+}
+
+#[test]
+fn test_jwk_debug_redacts_private_members() {
+    let jwk: Jwk = serde_json::from_str(EC_JWK).unwrap();
+    let debug = format!("{:?}", jwk);
+    assert!(!debug.contains("jpsQnnGQmL-YBIffH1136cLSG7Dbjsg1ePh0l4IJ2NI"), "Debug should not show d: {}", debug);
+    assert!(debug.contains("gv4qSvn4RbNLi7T0a41CWgp35I1ff65BewVNCXRXG9I"), "Debug should show the public members: {}", debug);
+
+    // Exported private keys and shared secrets are redacted too
+    for algorithm in [Algorithm::RS256, Algorithm::EdDSA, Algorithm::HS256] {
+        let jwk = EncodingKey::generate(&algorithm).unwrap().to_jwk().unwrap();
+        let secret = match &jwk.key {
+            JwkKey::Rsa { d: Some(d), .. } | JwkKey::Okp { d: Some(d), .. } => d.clone(),
+            JwkKey::Oct { k } => k.clone(),
+            key => panic!("Expected a private JWK, got {:?}", key),
+        };
+        let debug = format!("{:?}", jwk);
+        assert!(!debug.contains(secret.as_str()), "Debug should not show the {} private key: {}", algorithm, debug);
+    }
+}
+
+#[test]
+fn test_jwk_rejected() {
+    // Keys marked for encryption, or only for verifying, cannot sign
+    let mut jwk: Jwk = serde_json::from_str(EC_JWK).unwrap();
+    jwk.key_use = Some("enc".to_string());
+    assert_eq!(EncodingKey::from_jwk(&jwk).err(), Some(KeyError::IncompatibleKey("JWK 'use' or 'key_ops' does not allow signing".to_string())));
+    assert_eq!(DecodingKey::from_jwk(&jwk).err(), Some(KeyError::IncompatibleKey("JWK 'use' or 'key_ops' does not allow verifying".to_string())));
+    jwk.key_use = None;
+    jwk.key_ops = Some(vec!["verify".to_string()]);
+    assert!(EncodingKey::from_jwk(&jwk).is_err());
+    assert!(DecodingKey::from_jwk(&jwk).is_ok());
+
+    // A public JWK cannot sign
+    jwk.key_ops = None;
+    let public_jwk = jwk.to_public().unwrap();
+    assert_eq!(EncodingKey::from_jwk(&public_jwk).err(), Some(KeyError::InvalidKey("JWK is missing the private key 'd'".to_string())));
+
+    // The private key must belong to the public key
+    let other = EncodingKey::from_ec_pem(&std::fs::read_to_string("src/tests/test_es256_private.pem").unwrap()).unwrap().to_jwk().unwrap();
+    let JwkKey::Ec { d, .. } = other.key else { panic!("Expected an EC JWK") };
+    let JwkKey::Ec { crv, x, y, .. } = public_jwk.key else { panic!("Expected an EC JWK") };
+    let mismatched = Jwk::new(JwkKey::Ec { crv, x, y, d });
+    assert_eq!(EncodingKey::from_jwk(&mismatched).err(), Some(KeyError::InvalidKey("JWK public key does not match the private key".to_string())));
+
+    // Unknown key types and curves
+    assert!(serde_json::from_str::<Jwk>(r#"{"kty":"XYZ","k":"AAAA"}"#).is_err());
+    let jwk: Jwk = serde_json::from_str(r#"{"kty":"EC","crv":"P-192","x":"AAAA","y":"AAAA"}"#).unwrap();
+    assert_eq!(DecodingKey::from_jwk(&jwk).err(), Some(KeyError::InvalidKey("Unsupported EC curve \"P-192\"".to_string())));
+
+    // Shared secrets have no public key to publish
+    assert!(matches!(EncodingKey::from_secret(b"a-secret-that-is-32-bytes-long!!").unwrap().to_public_jwk(), Err(KeyError::IncompatibleKey(_))));
+}
+
+#[test]
+fn test_rsa_jwk_without_primes_successful() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let encoding_key = EncodingKey::from_rsa_pem(&std::fs::read_to_string("src/tests/test_private.pem").unwrap()).unwrap();
+
+    // Only `n`, `e` and `d` are required, the primes are recovered
+    let mut jwk = encoding_key.to_jwk().unwrap();
+    if let JwkKey::Rsa { p, q, dp, dq, qi, .. } = &mut jwk.key {
+        (*p, *q, *dp, *dq, *qi) = (None, None, None, None, None);
+    }
+    let signed_token = crate::sign_with_key(&Header::new(Algorithm::RS256), &claims, &EncodingKey::from_jwk(&jwk).unwrap()).unwrap();
+    let public_key = std::fs::read_to_string("src/tests/test_public.pem").unwrap();
+    let verified: Claims = crate::verify(&signed_token, &public_key).unwrap();
+    assert_eq!(verified, claims);
+}
+
+/// Returns the key with its public JWK, which has the given key ID
+fn keyed(kid: &str, encoding_key: EncodingKey) -> (EncodingKey, Jwk) {
+    let mut jwk = encoding_key.to_public_jwk().unwrap();
+    jwk.kid = Some(kid.to_string());
+    (encoding_key, jwk)
+}
+
+fn sign_with_kid(algorithm: Algorithm, kid: Option<&str>, encoding_key: &EncodingKey) -> String {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let mut header = Header::new(algorithm);
+    header.kid = kid.map(str::to_string);
+    crate::sign_with_key(&header, &claims, encoding_key).unwrap()
+}
+
+#[test]
+fn test_jwks_kid_selection_successful() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let (rsa, rsa_jwk) = keyed("rsa-1", EncodingKey::from_rsa_pem(&std::fs::read_to_string("src/tests/test_private.pem").unwrap()).unwrap());
+    let (es256, es256_jwk) = keyed("ec-1", EncodingKey::from_ec_pem(&std::fs::read_to_string("src/tests/test_es256_private.pem").unwrap()).unwrap());
+    let (ed25519, ed25519_jwk) = keyed("ed-1", EncodingKey::from_ed_pem(&std::fs::read_to_string("src/tests/test_ed25519_private.pem").unwrap()).unwrap());
+    let jwks = JwkSet::new(vec![rsa_jwk, es256_jwk, ed25519_jwk]);
+    let keys = DecodingKeySet::from_jwk_set(&jwks).unwrap();
+
+    // The key is picked by the kid of the token
+    for (algorithm, kid, encoding_key) in [(Algorithm::RS256, "rsa-1", &rsa), (Algorithm::ES256, "ec-1", &es256), (Algorithm::EdDSA, "ed-1", &ed25519)] {
+        let signed_token = sign_with_kid(algorithm.clone(), Some(kid), encoding_key);
+        let verified: Claims = crate::verify_with_jwks(&signed_token, &keys).unwrap();
+        assert_eq!(verified, claims, "Token with kid {} should verify", kid);
+    }
+
+    // Without a kid, the keys of the right type are tried
+    let signed_token = sign_with_kid(Algorithm::ES256, None, &es256);
+    let verified: Claims = crate::verify_with_jwks(&signed_token, &keys).unwrap();
+    assert_eq!(verified, claims);
+
+    // The set survives serde
+    let jwks: JwkSet = serde_json::from_str(&serde_json::to_string(&jwks).unwrap()).unwrap();
+    let signed_token = sign_with_kid(Algorithm::PS256, Some("rsa-1"), &rsa);
+    let verified: Claims = crate::verify_with_jwks(&signed_token, &DecodingKeySet::from_jwk_set(&jwks).unwrap()).unwrap();
+    assert_eq!(verified, claims);
+}
+
+#[test]
+fn test_jwks_rejected() {
+    let (rsa, rsa_jwk) = keyed("rsa-1", EncodingKey::from_rsa_pem(&std::fs::read_to_string("src/tests/test_private.pem").unwrap()).unwrap());
+    let (es256, es256_jwk) = keyed("ec-1", EncodingKey::from_ec_pem(&std::fs::read_to_string("src/tests/test_es256_private.pem").unwrap()).unwrap());
+    let jwks = JwkSet::new(vec![rsa_jwk, es256_jwk]);
+    let keys = DecodingKeySet::from_jwk_set(&jwks).unwrap();
+
+    // A kid that is not in the set
+    let signed_token = sign_with_kid(Algorithm::RS256, Some("rsa-2"), &rsa);
+    let result: Result<Claims, _> = crate::verify_with_jwks(&signed_token, &keys);
+    assert_eq!(result.unwrap_err(), VerifyTokenError::UnknownKeyId("rsa-2".to_string()));
+
+    // A kid of a key of another type
+    let signed_token = sign_with_kid(Algorithm::RS256, Some("ec-1"), &rsa);
+    let result: Result<Claims, _> = crate::verify_with_jwks(&signed_token, &keys);
+    assert_eq!(result.unwrap_err(), VerifyTokenError::NoMatchingKey);
+
+    // No key for the algorithm
+    let signed_token = sign_with_kid(Algorithm::HS256, None, &EncodingKey::from_secret(b"your-256-bit-secret-for-the-jwks").unwrap());
+    let result: Result<Claims, _> = crate::verify_with_jwks(&signed_token, &keys);
+    assert_eq!(result.unwrap_err(), VerifyTokenError::NoMatchingKey);
+
+    // The right kid signed by another key
+    let (other, _) = keyed("ec-1", EncodingKey::from_jwk(&serde_json::from_str(EC_JWK).unwrap()).unwrap());
+    let signed_token = sign_with_kid(Algorithm::ES256, Some("ec-1"), &other);
+    let result: Result<Claims, _> = crate::verify_with_jwks(&signed_token, &keys);
+    assert_eq!(result.unwrap_err(), VerifyTokenError::InvalidSignature);
+
+    // Keys only for encryption are never used
+    let mut jwks = jwks;
+    jwks.keys[1].key_use = Some("enc".to_string());
+    let keys = DecodingKeySet::from_jwk_set(&jwks).unwrap();
+    let signed_token = sign_with_kid(Algorithm::ES256, Some("ec-1"), &es256);
+    let result: Result<Claims, _> = crate::verify_with_jwks(&signed_token, &keys);
+    assert!(matches!(result.unwrap_err(), VerifyTokenError::IncompatibleKey(_)));
+
+    // Keys are read when the set is, so a set with a key that cannot be read is rejected before any token is verified
+    jwks.keys[1].key_use = None;
+    jwks.keys[1].key = JwkKey::Ec { crv: "P-256".to_string(), x: "AAAA".to_string(), y: "AAAA".to_string(), d: None };
+    assert!(matches!(DecodingKeySet::from_jwk_set(&jwks), Err(KeyError::InvalidKey(_))));
+}
+
+#[test]
+fn test_jwks_skips_unsupported_keys() {
+    // Encryption keys of unknown types are common in published sets, and must not stop the others being read
+    let json = format!(r#"{{"keys":[{{"kty":"XYZ","kid":"unknown"}},{}]}}"#, EC_JWK);
+    let jwks: JwkSet = serde_json::from_str(&json).unwrap();
+    assert_eq!(jwks.keys, vec![serde_json::from_str::<Jwk>(EC_JWK).unwrap()]);
+
+    // A key of a supported type with a member missing is an error rather than being left out
+    let json = format!(r#"{{"keys":[{{"kty":"EC","crv":"P-256","x":"AAAA"}},{}]}}"#, EC_JWK);
+    assert!(serde_json::from_str::<JwkSet>(&json).is_err());
+    let json = format!(r#"{{"keys":[{{"kid":"no-type"}},{}]}}"#, EC_JWK);
+    assert!(serde_json::from_str::<JwkSet>(&json).is_err());
+}
+
+#[test]
+fn test_thumbprint_successful() {
+    // The example of RFC 7638 section 3.1, with members that are not part of the thumbprint
+    let jwk: Jwk = serde_json::from_str(r#"{"kty":"RSA","n":"0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw","e":"AQAB","alg":"RS256","kid":"2011-04-29"}"#).unwrap();
+    assert_eq!(jwk.thumbprint(), "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs");
+    assert_eq!(DecodingKey::from_jwk(&jwk).unwrap().thumbprint(), "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs");
+
+    // The example of RFC 9278 section 3
+    assert_eq!(jwk.thumbprint_uri(), "urn:ietf:params:oauth:jwk-thumbprint:sha-256:NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs");
+
+    // The Ed25519 example of RFC 8037 appendix A.3, the private key has the same thumbprint as the public key
+    let jwk: Jwk = serde_json::from_str(r#"{"kty":"OKP","crv":"Ed25519","d":"nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#).unwrap();
+    assert_eq!(jwk.thumbprint(), "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k");
+    assert_eq!(EncodingKey::from_jwk(&jwk).unwrap().thumbprint(), "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k");
+    assert_eq!(jwk.to_public().unwrap().thumbprint(), "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k");
+
+    // The same key read from PEM has the same thumbprint as its JWK
+    for (private_path, public_path) in [("src/tests/test_es512_private.pem", "src/tests/test_es512_public.pem"), ("src/tests/test_ed448_private.pem", "src/tests/test_ed448_public.pem")] {
+        let encoding_key = EncodingKey::from_ec_pem(&std::fs::read_to_string(private_path).unwrap())
+            .or_else(|_| EncodingKey::from_ed_pem(&std::fs::read_to_string(private_path).unwrap()))
+            .unwrap();
+        let decoding_key = DecodingKey::from_ec_pem(&std::fs::read_to_string(public_path).unwrap())
+            .or_else(|_| DecodingKey::from_ed_pem(&std::fs::read_to_string(public_path).unwrap()))
+            .unwrap();
+        assert_eq!(encoding_key.thumbprint(), decoding_key.thumbprint());
+        assert_eq!(encoding_key.thumbprint(), encoding_key.to_jwk().unwrap().thumbprint());
+    }
+}
+
+#[test]
+fn test_thumbprint_kid_successful() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let encoding_key = EncodingKey::from_ec_pem(&std::fs::read_to_string("src/tests/test_es256_private.pem").unwrap()).unwrap().with_thumbprint_kid();
+    let thumbprint = encoding_key.thumbprint();
+    assert_eq!(encoding_key.kid(), Some(thumbprint.as_str()));
+
+    // The thumbprint is stamped into the header and picks the key from a set
+    let signed_token = crate::sign_with_key(&Header::new(Algorithm::ES256), &claims, &encoding_key).unwrap();
+    let header = crate::decode_header(signed_token.split('.').next().unwrap()).unwrap();
+    assert_eq!(header.kid, Some(thumbprint.clone()));
+
+    let public_jwk = encoding_key.to_public_jwk().unwrap();
+    assert_eq!(public_jwk.kid, Some(thumbprint.clone()), "Exported JWKs carry the key ID");
+    let verified: Claims = crate::verify_with_jwks(&signed_token, &DecodingKeySet::from_jwk_set(&JwkSet::new(vec![public_jwk])).unwrap()).unwrap();
+    assert_eq!(verified, claims);
+
+    // A kid in the header is kept
+    let mut header = Header::new(Algorithm::ES256);
+    header.kid = Some("chosen".to_string());
+    let signed_token = crate::sign_with_key(&header, &claims, &encoding_key).unwrap();
+    let header = crate::decode_header(signed_token.split('.').next().unwrap()).unwrap();
+    assert_eq!(header.kid.as_deref(), Some("chosen"));
+}
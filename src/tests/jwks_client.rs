@@ -0,0 +1,174 @@
+#![allow(unused)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Algorithm, EncodingKey, Header, HttpResponse, JwkSet, JwksClient, JwksError, VerifyTokenError};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Claims {
+    exp: usize,
+    sub: String,
+}
+
+const JWKS_URL: &str = "https://example.com/.well-known/jwks.json";
+
+/// Stands in for the identity provider, serving whatever response the test sets and counting the requests
+#[derive(Default)]
+struct StubServer {
+    response: Mutex<Option<Result<HttpResponse, String>>>,
+    requests: AtomicUsize,
+    /// Held by a test to keep requests waiting for a response
+    slow: Mutex<()>,
+}
+
+impl StubServer {
+    fn serve(&self, response: Result<HttpResponse, String>) {
+        *self.response.lock().unwrap() = Some(response);
+    }
+
+    fn serve_keys(&self, keys: &[&EncodingKey], cache_control: &str) {
+        let jwks = JwkSet::new(keys.iter().map(|key| key.to_public_jwk().unwrap()).collect());
+        self.serve(Ok(HttpResponse::new(200, serde_json::to_vec(&jwks).unwrap()).with_cache_control(cache_control)));
+    }
+
+    fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+
+    fn wait_for_requests(&self, requests: usize) {
+        while self.requests() < requests {
+            thread::yield_now();
+        }
+    }
+}
+
+/// A client of the stub server, with the minimum refresh interval given
+fn client(server: &Arc<StubServer>, min_refresh_interval: Duration) -> JwksClient {
+    let server = server.clone();
+    JwksClient::new(JWKS_URL, move |url: &str| {
+        assert_eq!(url, JWKS_URL);
+        server.requests.fetch_add(1, Ordering::SeqCst);
+        drop(server.slow.lock().unwrap());
+        server.response.lock().unwrap().clone().unwrap()
+    }).with_min_refresh_interval(min_refresh_interval)
+}
+
+#[test]
+fn test_jwks_client_cache_successful() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let server = Arc::new(StubServer::default());
+    let first = EncodingKey::generate(&Algorithm::ES256).unwrap();
+    server.serve_keys(&[&first], "public, max-age=3600");
+
+    // Nothing is fetched until the keys are needed, and then they are cached for their max-age
+    let client = client(&server, Duration::ZERO);
+    assert_eq!(server.requests(), 0);
+    let first_token = crate::sign_with_key(&Header::new(Algorithm::ES256), &claims, &first).unwrap();
+    for _ in 0..3 {
+        let verified: Claims = crate::verify_with_jwks_client(&first_token, &client).unwrap();
+        assert_eq!(verified, claims);
+    }
+    assert_eq!(server.requests(), 1, "Cached keys should not be fetched again");
+
+    // A new key ID triggers a refresh
+    let second = EncodingKey::generate(&Algorithm::ES256).unwrap();
+    server.serve_keys(&[&first, &second], "max-age=3600");
+    let second_token = crate::sign_with_key(&Header::new(Algorithm::ES256), &claims, &second).unwrap();
+    let verified: Claims = crate::verify_with_jwks_client(&second_token, &client).unwrap();
+    assert_eq!(verified, claims);
+    assert_eq!(server.requests(), 2);
+
+    // Without caching, keys are fetched every time
+    server.serve_keys(&[&first], "no-cache");
+    client.refresh().unwrap();
+    client.keys().unwrap();
+    client.keys().unwrap();
+    assert_eq!(server.requests(), 5);
+}
+
+#[test]
+fn test_jwks_client_rate_limit_successful() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let server = Arc::new(StubServer::default());
+    let known = EncodingKey::generate(&Algorithm::EdDSA).unwrap();
+    server.serve_keys(&[&known], "no-store");
+    let client = client(&server, Duration::from_secs(3600));
+
+    // Tokens with unknown key IDs, or keys that may not be cached, do not cause more fetches within the interval
+    let unknown = EncodingKey::generate(&Algorithm::EdDSA).unwrap();
+    let unknown_token = crate::sign_with_key(&Header::new(Algorithm::EdDSA), &claims, &unknown).unwrap();
+    for _ in 0..3 {
+        let result = crate::verify_with_jwks_client::<Claims>(&unknown_token, &client);
+        assert_eq!(result, Err(VerifyTokenError::UnknownKeyId(unknown.kid().unwrap().to_string())));
+    }
+    client.keys().unwrap();
+    assert_eq!(server.requests(), 1);
+}
+
+#[test]
+fn test_jwks_client_unavailable_rejected() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let server = Arc::new(StubServer::default());
+    let client = client(&server, Duration::ZERO);
+
+    // Without any keys, the error of the fetch is returned
+    let key = EncodingKey::generate(&Algorithm::ES384).unwrap();
+    let signed_token = crate::sign_with_key(&Header::new(Algorithm::ES384), &claims, &key).unwrap();
+    server.serve(Err("Connection refused".to_string()));
+    assert_eq!(crate::verify_with_jwks_client::<Claims>(&signed_token, &client), Err(VerifyTokenError::FetchingKeys(JwksError::Fetch("Connection refused".to_string()))));
+    server.serve(Ok(HttpResponse::new(200, b"<html>".to_vec())));
+    assert!(matches!(client.keys(), Err(JwksError::InvalidJwks(_))));
+
+    // Once fetched, the last keys are used while the endpoint is down
+    server.serve_keys(&[&key], "max-age=0");
+    client.keys().unwrap();
+    server.serve(Ok(HttpResponse::new(503, Vec::new())));
+    let verified: Claims = crate::verify_with_jwks_client(&signed_token, &client).unwrap();
+    assert_eq!(verified, claims);
+    server.serve(Err("Timed out".to_string()));
+    let verified: Claims = crate::verify_with_jwks_client(&signed_token, &client).unwrap();
+    assert_eq!(verified, claims);
+    assert_eq!(server.requests(), 5, "The endpoint should be retried while it is down");
+}
+
+#[test]
+fn test_jwks_client_slow_fetch_successful() {
+    let server = Arc::new(StubServer::default());
+    let first = EncodingKey::generate(&Algorithm::ES256).unwrap();
+    server.serve_keys(&[&first], "max-age=3600");
+    let client = Arc::new(client(&server, Duration::ZERO));
+
+    // Requests without any keys wait for the fetch in flight rather than fetching again
+    let slow = server.slow.lock().unwrap();
+    let waiting: Vec<_> = (0..2).map(|_| {
+        let client = client.clone();
+        thread::spawn(move || client.keys())
+    }).collect();
+    server.wait_for_requests(1);
+    thread::sleep(Duration::from_millis(50));
+    drop(slow);
+    for request in waiting {
+        assert_eq!(request.join().unwrap().unwrap().len(), 1);
+    }
+    assert_eq!(server.requests(), 1);
+
+    // While keys are being fetched again, other requests use the cached keys straight away
+    let second = EncodingKey::generate(&Algorithm::ES256).unwrap();
+    server.serve_keys(&[&first, &second], "max-age=3600");
+    let slow = server.slow.lock().unwrap();
+    let refreshing = {
+        let client = client.clone();
+        thread::spawn(move || client.refresh())
+    };
+    server.wait_for_requests(2);
+    assert_eq!(client.refresh().unwrap().len(), 1);
+    assert_eq!(client.keys().unwrap().len(), 1);
+    drop(slow);
+    assert_eq!(refreshing.join().unwrap().unwrap().len(), 2);
+    assert_eq!(client.keys().unwrap().len(), 2);
+    assert_eq!(server.requests(), 2);
+}
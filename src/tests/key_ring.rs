@@ -0,0 +1,83 @@
+#![allow(unused)]
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Algorithm, DecodingKey, EncodingKey, Header, KeyRing, KeyRingEntry, SignTokenError, VerifyTokenError};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Claims {
+    exp: usize,
+    sub: String,
+}
+
+#[test]
+fn test_key_ring_rotation_successful() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let header = Header::new(Algorithm::ES256);
+    let mut key_ring = KeyRing::new("first", EncodingKey::generate(&Algorithm::ES256).unwrap());
+
+    // The active key ID is stamped into the token, replacing any in the header
+    let old_token = crate::sign_with_key_ring(&Header { kid: Some("other".to_string()), ..header.clone() }, &claims, &key_ring).unwrap();
+    assert_eq!(crate::decode_header(old_token.split('.').next().unwrap()).unwrap().kid, Some("first".to_string()));
+
+    // After rotation the new key signs, and tokens from the old key still verify until it is retired
+    let retire_after = SystemTime::now() + Duration::from_secs(60);
+    key_ring.rotate("second", EncodingKey::generate(&Algorithm::ES256).unwrap(), Some(retire_after));
+    assert_eq!(key_ring.active_kid(), "second");
+    let new_token = crate::sign_with_key_ring(&header, &claims, &key_ring).unwrap();
+    assert_eq!(crate::decode_header(new_token.split('.').next().unwrap()).unwrap().kid, Some("second".to_string()));
+
+    let verified: Claims = crate::verify_with_key_ring(&old_token, &key_ring).unwrap();
+    assert_eq!(verified, claims, "Tokens from the retired key should verify");
+    let verified: Claims = crate::verify_with_key_ring(&new_token, &key_ring).unwrap();
+    assert_eq!(verified, claims, "Tokens from the active key should verify");
+    assert_eq!(key_ring.to_jwk_set().keys.len(), 2, "Both keys should be published");
+
+    // Once removed, the old key ID is unknown, and the active key cannot be removed
+    assert!(key_ring.remove("second").is_none());
+    assert!(key_ring.remove("first").is_some());
+    assert_eq!(crate::verify_with_key_ring::<Claims>(&old_token, &key_ring), Err(VerifyTokenError::UnknownKeyId("first".to_string())));
+
+    // Tokens without a key ID are tried against every valid key
+    let no_kid_token = crate::sign_with_key(&header, &claims, &EncodingKey::from_jwk(&key_ring_private_jwk(&key_ring)).unwrap()).unwrap();
+    let verified: Claims = crate::verify_with_key_ring(&no_kid_token, &key_ring).unwrap();
+    assert_eq!(verified, claims);
+}
+
+/// The private JWK of the active key of a key ring, without its key ID
+fn key_ring_private_jwk(key_ring: &KeyRing) -> crate::Jwk {
+    let mut jwk = key_ring.signing_key().to_jwk().unwrap();
+    jwk.kid = None;
+    jwk
+}
+
+#[test]
+fn test_key_ring_validity_rejected() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let header = Header::new(Algorithm::HS256);
+    let retired = EncodingKey::from_secret(b"a-retired-secret-of-32-bytes-len").unwrap();
+    let retired_token = crate::sign_with_key(&Header { kid: Some("retired".to_string()), ..header.clone() }, &claims, &retired).unwrap();
+    let future = EncodingKey::from_secret(b"a-future-secret-of-32-bytes-long").unwrap();
+    let future_token = crate::sign_with_key(&Header { kid: Some("future".to_string()), ..header.clone() }, &claims, &future).unwrap();
+
+    let mut key_ring = KeyRing::new("active", EncodingKey::from_secret(b"the-active-secret-of-32-bytes-ok").unwrap());
+    key_ring.add(KeyRingEntry::new("retired", retired.to_decoding_key()).with_not_after(SystemTime::now() - Duration::from_secs(60)));
+    key_ring.add(KeyRingEntry::new("future", future.to_decoding_key()).with_not_before(SystemTime::now() + Duration::from_secs(60)));
+
+    // Keys outside their window do not verify, even when the token names them
+    assert_eq!(crate::verify_with_key_ring::<Claims>(&retired_token, &key_ring), Err(VerifyTokenError::KeyNotValid("retired".to_string())));
+    assert_eq!(crate::verify_with_key_ring::<Claims>(&future_token, &key_ring), Err(VerifyTokenError::KeyNotValid("future".to_string())));
+
+    // Nor are they tried for tokens without a key ID
+    let retired_no_kid = crate::sign_with_key(&header, &claims, &retired).unwrap();
+    assert_eq!(crate::verify_with_key_ring::<Claims>(&retired_no_kid, &key_ring), Err(VerifyTokenError::InvalidSignature));
+
+    // Shared secrets are never published
+    assert!(key_ring.to_jwk_set().keys.is_empty());
+
+    // An active key outside its window does not sign
+    key_ring.rotate("future", future, None);
+    key_ring.add(KeyRingEntry::new("future", key_ring.get("future").unwrap().key.clone()).with_not_before(SystemTime::now() + Duration::from_secs(60)));
+    assert!(matches!(crate::sign_with_key_ring(&header, &claims, &key_ring), Err(SignTokenError::InvalidKey(_))));
+}
@@ -0,0 +1,152 @@
+#![allow(unused)]
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Algorithm, EncodingKey, Header, JwkKey, JwkSet, KeyError, KeySource, VerifyTokenError};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Claims {
+    exp: usize,
+    sub: String,
+}
+
+/// An empty directory for a test, removed first in case an earlier run left it behind
+fn test_directory(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("super_simple_jwt_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+    path
+}
+
+/// A JWKS with the public key of a signing key
+fn jwks_json(key: &EncodingKey) -> String {
+    serde_json::to_string(&JwkSet::new(vec![key.to_public_jwk().unwrap()])).unwrap()
+}
+
+#[test]
+fn test_key_source_jwks_reload_successful() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let directory = test_directory("jwks_reload");
+    let path = directory.join("jwks.json");
+
+    let first = EncodingKey::generate(&Algorithm::ES256).unwrap();
+    std::fs::write(&path, jwks_json(&first)).unwrap();
+    let key_source = KeySource::from_jwks_file(&path).unwrap();
+    let first_token = crate::sign_with_key(&Header::new(Algorithm::ES256), &claims, &first).unwrap();
+    let verified: Claims = crate::verify_with_jwks(&first_token, &key_source.keys()).unwrap();
+    assert_eq!(verified, claims);
+    assert!(!key_source.reload().unwrap(), "Unchanged files should not be reloaded");
+
+    // A broken file is reported once and the last good keys are kept
+    std::fs::write(&path, "{\"keys\":[").unwrap();
+    assert!(matches!(key_source.reload(), Err(KeyError::InvalidKey(_))));
+    assert!(!key_source.reload().unwrap(), "The same broken file should only be reported once");
+    let verified: Claims = crate::verify_with_jwks(&first_token, &key_source.keys()).unwrap();
+    assert_eq!(verified, claims, "The last good keys should be kept");
+
+    // A set without keys is also kept out
+    std::fs::write(&path, "{\"keys\":[]}").unwrap();
+    assert!(key_source.reload().is_err());
+
+    // New keys are swapped in, while callers holding the old set keep it
+    let old_keys = key_source.keys();
+    let second = EncodingKey::generate(&Algorithm::ES256).unwrap();
+    std::fs::write(&path, jwks_json(&second)).unwrap();
+    assert!(key_source.reload().unwrap());
+    let second_token = crate::sign_with_key(&Header::new(Algorithm::ES256), &claims, &second).unwrap();
+    let verified: Claims = crate::verify_with_jwks(&second_token, &key_source.keys()).unwrap();
+    assert_eq!(verified, claims);
+    assert_eq!(crate::verify_with_jwks::<Claims>(&first_token, &key_source.keys()), Err(VerifyTokenError::UnknownKeyId(first.kid().unwrap().to_string())));
+    assert!(crate::verify_with_jwks::<Claims>(&first_token, &old_keys).is_ok());
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_key_source_pem_directory_successful() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let directory = test_directory("pem_directory");
+    std::fs::copy("src/tests/test_public.pem", directory.join("rsa.pem")).unwrap();
+    std::fs::copy("src/tests/test_es256_public.pem", directory.join("ec.pem")).unwrap();
+    std::fs::write(directory.join("README"), "Not a key").unwrap();
+
+    // The key ID is the file name
+    let key_source = KeySource::from_pem_directory(&directory).unwrap();
+    let kids: Vec<_> = key_source.keys().jwks().map(|jwk| jwk.kid.clone().unwrap()).collect();
+    assert_eq!(kids, ["ec", "rsa"]);
+
+    let private_key = std::fs::read_to_string("src/tests/test_es256_private.pem").unwrap();
+    let signed_token = crate::sign(&Header { kid: Some("ec".to_string()), ..Header::new(Algorithm::ES256) }, &claims, &private_key).unwrap();
+    let verified: Claims = crate::verify_with_jwks(&signed_token, &key_source.keys()).unwrap();
+    assert_eq!(verified, claims);
+
+    // Private keys are not accepted
+    std::fs::copy("src/tests/test_es256_private.pem", directory.join("private.pem")).unwrap();
+    assert!(matches!(key_source.reload(), Err(KeyError::InvalidKey(message)) if message.contains("private.pem")));
+    assert_eq!(key_source.keys().len(), 2);
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_key_source_watch_successful() {
+    let directory = test_directory("watch");
+    let path = directory.join("jwks.json");
+    let first = EncodingKey::generate(&Algorithm::EdDSA).unwrap();
+    std::fs::write(&path, jwks_json(&first)).unwrap();
+    let key_source = KeySource::from_jwks_file(&path).unwrap();
+
+    let (errors, received) = mpsc::channel();
+    let watcher = key_source.watch(Duration::from_millis(10), move |err| errors.send(err).unwrap());
+
+    // Errors are reported through the callback
+    std::fs::write(&path, "not json").unwrap();
+    assert!(matches!(received.recv_timeout(Duration::from_secs(10)), Ok(KeyError::InvalidKey(_))));
+
+    // Changes are picked up without calling reload
+    let second = EncodingKey::generate(&Algorithm::EdDSA).unwrap();
+    std::fs::write(&path, jwks_json(&second)).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while key_source.keys().find(second.kid().unwrap()).next().is_none() {
+        assert!(Instant::now() < deadline, "The new key should be picked up");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    drop(watcher);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_key_source_corrupted_key_rejected() {
+    let directory = test_directory("corrupted_key");
+    let path = directory.join("jwks.json");
+    let first = EncodingKey::generate(&Algorithm::ES256).unwrap();
+    std::fs::write(&path, jwks_json(&first)).unwrap();
+    let key_source = KeySource::from_jwks_file(&path).unwrap();
+
+    let (errors, received) = mpsc::channel();
+    let watcher = key_source.watch(Duration::from_millis(10), move |err| errors.send(err).unwrap());
+
+    // A set with one key that cannot be read is reported, and the last good keys are kept
+    let second = EncodingKey::generate(&Algorithm::ES256).unwrap();
+    let mut corrupted = EncodingKey::generate(&Algorithm::ES256).unwrap().to_public_jwk().unwrap();
+    if let JwkKey::Ec { x, .. } = &mut corrupted.key {
+        *x = "AAAA".to_string();
+    }
+    std::fs::write(&path, serde_json::to_string(&JwkSet::new(vec![second.to_public_jwk().unwrap(), corrupted])).unwrap()).unwrap();
+    assert!(matches!(received.recv_timeout(Duration::from_secs(10)), Ok(KeyError::InvalidKey(_))));
+    assert!(key_source.keys().find(first.kid().unwrap()).next().is_some(), "The last good keys should be kept");
+    assert!(key_source.keys().find(second.kid().unwrap()).next().is_none());
+
+    // So is a key with a misspelt member, rather than it being left out of the set
+    std::fs::write(&path, jwks_json(&second).replace("\"crv\"", "\"curve\"")).unwrap();
+    assert!(matches!(received.recv_timeout(Duration::from_secs(10)), Ok(KeyError::InvalidKey(_))));
+    assert!(key_source.keys().find(first.kid().unwrap()).next().is_some(), "The last good keys should be kept");
+    assert_eq!(key_source.keys().len(), 1);
+
+    drop(watcher);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
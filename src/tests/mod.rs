@@ -1,4 +1,18 @@
+// The header encoding test predates clippy being run on the tests
+#[allow(clippy::assertions_on_constants)]
 pub mod encoding;
 pub mod decoding;
 pub mod signing;
-pub mod verfying;
\ No newline at end of file
+pub mod verfying;
+pub mod keys;
+pub mod jwk;
+pub mod jwks_client;
+pub mod x5c;
+pub mod key_ring;
+pub mod key_source;
+#[cfg(any(test, feature = "test-util"))]
+pub mod signer;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+#[cfg(feature = "dangerous-unsecured")]
+pub mod unsecured;
\ No newline at end of file
@@ -0,0 +1,65 @@
+#![allow(unused)]
+use p256::ecdsa::signature::Signer as _;
+use serde::{Deserialize, Serialize};
+
+use crate::signing::pkcs11::jose_signature;
+use crate::{Algorithm, Header, Pkcs11Key, Pkcs11Module, Pkcs11Signer, SignTokenError};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Claims {
+    exp: usize,
+    sub: String,
+}
+
+#[test]
+fn test_pkcs11_ecdsa_signature_conversion() {
+    // Modules that follow the standard already return R || S
+    let signing_key = p256::ecdsa::SigningKey::random(&mut rsa::rand_core::OsRng);
+    let signature: p256::ecdsa::Signature = signing_key.sign(b"header.claims");
+    assert_eq!(jose_signature(&signature.to_bytes(), 32).unwrap(), signature.to_bytes().to_vec());
+
+    // DER signatures are converted, including P-521 signatures with a long form length
+    assert_eq!(jose_signature(signature.to_der().as_bytes(), 32).unwrap(), signature.to_bytes().to_vec());
+    let signing_key = p521::ecdsa::SigningKey::random(&mut rsa::rand_core::OsRng);
+    let signature: p521::ecdsa::Signature = signing_key.sign(b"header.claims");
+    assert_eq!(jose_signature(signature.to_der().as_bytes(), 66).unwrap(), signature.to_bytes().to_vec());
+
+    assert!(jose_signature(&[0x30, 0x03, 0x02, 0x01], 32).is_err(), "Truncated DER should be rejected");
+}
+
+/// Signs and verifies with keys in SoftHSM2, run with `cargo test --features pkcs11 -- --ignored pkcs11`
+///
+/// The token and keys are created with:
+/// ```sh
+/// softhsm2-util --init-token --free --label jwt-test --so-pin 5678 --pin 1234
+/// pkcs11-tool --module $PKCS11_MODULE --token-label jwt-test --login --pin 1234 --keypairgen --key-type rsa:2048 --label jwt-rsa
+/// pkcs11-tool --module $PKCS11_MODULE --token-label jwt-test --login --pin 1234 --keypairgen --key-type EC:prime256v1 --label jwt-ec
+/// ```
+/// `PKCS11_MODULE` defaults to `/usr/lib/softhsm/libsofthsm2.so`.
+#[test]
+#[ignore = "requires SoftHSM2 with the jwt-test token"]
+fn test_pkcs11_softhsm_signing_successful() {
+    let path = std::env::var("PKCS11_MODULE").unwrap_or_else(|_| "/usr/lib/softhsm/libsofthsm2.so".to_string());
+    let module = Pkcs11Module::load(path).unwrap();
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+
+    let rsa_signer = Pkcs11Signer::open(&module, "jwt-test", "1234", Pkcs11Key::Label("jwt-rsa".to_string())).unwrap();
+    let rsa_key = rsa_signer.verifying_key().unwrap();
+    for algorithm in [Algorithm::RS256, Algorithm::RS384, Algorithm::RS512, Algorithm::PS256, Algorithm::PS384, Algorithm::PS512] {
+        let token = crate::sign_with_key(&Header::new(algorithm.clone()), &claims, &rsa_signer).unwrap();
+        let verified: Claims = crate::verify_with_key(&token, &rsa_key).unwrap();
+        assert_eq!(verified, claims, "{} should verify", algorithm);
+    }
+
+    let ec_signer = Pkcs11Signer::open(&module, "jwt-test", "1234", Pkcs11Key::Label("jwt-ec".to_string())).unwrap().with_kid("hsm");
+    let token = crate::sign_with_key(&Header::new(Algorithm::ES256), &claims, &ec_signer).unwrap();
+    let verified: Claims = crate::verify_with_key(&token, &ec_signer.verifying_key().unwrap()).unwrap();
+    assert_eq!(verified, claims);
+    assert_eq!(crate::decode_header(token.split('.').next().unwrap()).unwrap().kid, Some("hsm".to_string()));
+
+    // The key must match the algorithm
+    let result = crate::sign_with_key(&Header::new(Algorithm::ES384), &claims, &ec_signer);
+    assert!(matches!(result, Err(SignTokenError::InvalidKey(_))));
+    let result = crate::sign_with_key(&Header::new(Algorithm::ES256), &claims, &rsa_signer);
+    assert!(matches!(result, Err(SignTokenError::InvalidKey(_))));
+}
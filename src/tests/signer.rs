@@ -0,0 +1,97 @@
+#![allow(unused)]
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Algorithm, AsyncSigner, DecodingKey, EncodingKey, Header, MockSigner, SignTokenError, Verifier, VerifyTokenError};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Claims {
+    exp: usize,
+    sub: String,
+}
+
+/// Polls a future that never waits to completion, as none of the futures in these tests do
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("The future should not wait"),
+    }
+}
+
+/// An asynchronous signer, standing in for a key service called over the network
+struct RemoteSigner {
+    key: EncodingKey,
+}
+
+impl AsyncSigner for RemoteSigner {
+    async fn sign(&self, signing_input: &[u8], algorithm: &Algorithm) -> Result<Vec<u8>, SignTokenError> {
+        crate::Signer::sign(&self.key, signing_input, algorithm)
+    }
+
+    fn kid(&self) -> Option<&str> {
+        Some("remote")
+    }
+}
+
+/// A verifier that rejects every signature
+struct RejectingVerifier;
+
+impl Verifier for RejectingVerifier {
+    fn verify(&self, _signing_input: &[u8], _signature: &[u8], _algorithm: &Algorithm) -> Result<bool, VerifyTokenError> {
+        Ok(false)
+    }
+}
+
+#[test]
+fn test_mock_signer_successful() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let signer = MockSigner::generate(&Algorithm::ES256).unwrap().with_kid("mock");
+
+    let token = crate::sign_with_key(&Header::new(Algorithm::ES256), &claims, &signer).unwrap();
+    assert_eq!(crate::decode_header(token.split('.').next().unwrap()).unwrap().kid, Some("mock".to_string()));
+
+    // The signer is given the header and claims exactly as they appear in the token
+    let signing_input = token.rsplit_once('.').unwrap().0;
+    assert_eq!(signer.signing_inputs(), vec![signing_input.as_bytes().to_vec()]);
+
+    let verified: Claims = crate::verify_with_key(&token, &signer.verifying_key()).unwrap();
+    assert_eq!(verified, claims);
+    assert_eq!(crate::verify_with_key::<Claims>(&token, &RejectingVerifier), Err(VerifyTokenError::InvalidSignature));
+}
+
+#[test]
+fn test_mock_signer_failure() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let signer = MockSigner::generate(&Algorithm::RS256).unwrap().with_failure("key service unavailable");
+
+    let result = crate::sign_with_key(&Header::new(Algorithm::RS256), &claims, &signer);
+    assert!(matches!(result, Err(SignTokenError::Signer(message)) if message == "key service unavailable"));
+    assert_eq!(signer.signing_inputs().len(), 1, "The failed request should be recorded");
+}
+
+#[test]
+fn test_async_signer_and_verifier_successful() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let key = EncodingKey::generate(&Algorithm::EdDSA).unwrap();
+    let decoding_key = key.to_decoding_key();
+    let signer = RemoteSigner { key };
+
+    let token = block_on(crate::sign_async(&Header::new(Algorithm::EdDSA), &claims, &signer)).unwrap();
+    assert_eq!(crate::decode_header(token.split('.').next().unwrap()).unwrap().kid, Some("remote".to_string()));
+
+    // The built-in keys work with the asynchronous functions too
+    let verified: Claims = block_on(crate::verify_async(&token, &decoding_key)).unwrap();
+    assert_eq!(verified, claims);
+    let hmac_key = EncodingKey::from_secret(b"a-secret-that-is-32-bytes-long!!").unwrap();
+    let token = block_on(crate::sign_async(&Header::new(Algorithm::HS256), &claims, &hmac_key)).unwrap();
+    let verified: Claims = block_on(crate::verify_async(&token, &hmac_key.to_decoding_key())).unwrap();
+    assert_eq!(verified, claims);
+
+    // A key of the wrong type is still rejected
+    let result = block_on(crate::verify_async::<Claims>(&token, &decoding_key));
+    assert!(matches!(result, Err(VerifyTokenError::IncompatibleKey(_))));
+}
@@ -0,0 +1,132 @@
+#![allow(unused)]
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{Algorithm, CertificateChainError, Header, TrustAnchors, VerifyTokenError};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Claims {
+    exp: usize,
+    sub: String,
+}
+
+/// Reads a PEM certificate from the tests directory as the standard base64 DER used in `x5c`
+fn read_x5c(name: &str) -> String {
+    let pem = std::fs::read_to_string(format!("src/tests/{}.pem", name)).expect("Failed to read PEM file");
+    let (_, document) = pkcs8::der::Document::from_pem(&pem).expect("Failed to decode PEM file");
+    STANDARD.encode(document.as_bytes())
+}
+
+fn read_trust_anchors(name: &str) -> TrustAnchors {
+    let pem = std::fs::read_to_string(format!("src/tests/{}.pem", name)).expect("Failed to read PEM file");
+    TrustAnchors::from_pem(&pem).unwrap()
+}
+
+/// Signs a token with the leaf key, carrying the given chain
+fn sign_with_chain(algorithm: Algorithm, private_key_path: &str, chain: &[&str]) -> String {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let private_key = std::fs::read_to_string(private_key_path).expect("Failed to read PEM file");
+    let mut header = Header::new(algorithm);
+    header.x5c = Some(chain.iter().map(|name| read_x5c(name)).collect());
+    crate::sign(&header, &claims, &private_key).unwrap()
+}
+
+#[test]
+fn test_x5c_verify_successful() {
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let trust_anchors = read_trust_anchors("test_x5c_root");
+
+    // RSA leaf, RSA intermediate and P-384 root, the root does not need to be in the chain
+    let signed_token = sign_with_chain(Algorithm::RS256, "src/tests/test_private.pem", &["test_x5c_leaf", "test_x5c_inter"]);
+    let verified: Claims = crate::verify_with_x5c(&signed_token, &trust_anchors).unwrap();
+    assert_eq!(verified, claims, "Token should verify with the leaf of a trusted chain");
+
+    // But it may be
+    let signed_token = sign_with_chain(Algorithm::RS256, "src/tests/test_private.pem", &["test_x5c_leaf", "test_x5c_inter", "test_x5c_root"]);
+    let verified: Claims = crate::verify_with_x5c(&signed_token, &trust_anchors).unwrap();
+    assert_eq!(verified, claims, "Token should verify with a chain that includes the trust anchor");
+
+    // Ed25519 leaf signed directly by an Ed25519 root
+    let signed_token = sign_with_chain(Algorithm::EdDSA, "src/tests/test_ed25519_private.pem", &["test_x5c_edleaf"]);
+    let verified: Claims = crate::verify_with_x5c(&signed_token, &read_trust_anchors("test_x5c_edroot")).unwrap();
+    assert_eq!(verified, claims, "Token should verify with an Ed25519 chain");
+}
+
+#[test]
+fn test_x5c_header_round_trip() {
+    let leaf = STANDARD.decode(read_x5c("test_x5c_leaf")).unwrap();
+    let mut header = Header::new(Algorithm::RS256);
+    header.x5c = Some(vec![read_x5c("test_x5c_leaf"), read_x5c("test_x5c_inter")]);
+    header.x5t_s256 = Some(URL_SAFE_NO_PAD.encode(Sha256::digest(&leaf)));
+
+    let encoded = crate::encode_header(&header).unwrap();
+    let decoded = crate::decode_header(&encoded).unwrap();
+    assert_eq!(decoded.x5c, header.x5c);
+    assert_eq!(decoded.x5t, None);
+    assert_eq!(decoded.x5t_s256, header.x5t_s256);
+
+    // The matching thumbprint is accepted
+    let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+    let private_key = std::fs::read_to_string("src/tests/test_private.pem").expect("Failed to read PEM file");
+    let signed_token = crate::sign(&header, &claims, &private_key).unwrap();
+    let verified: Claims = crate::verify_with_x5c(&signed_token, &read_trust_anchors("test_x5c_root")).unwrap();
+    assert_eq!(verified, claims);
+}
+
+#[test]
+fn test_x5c_chain_rejected() {
+    let trust_anchors = read_trust_anchors("test_x5c_root");
+    let chains: [(&[&str], CertificateChainError); 6] = [
+        (&[], CertificateChainError::MissingChain),
+        (&["test_x5c_leaf", "test_x5c_inter_expired"], CertificateChainError::Expired(1)),
+        (&["test_x5c_leaf", "test_x5c_inter_notca"], CertificateChainError::NotCa(1)),
+        (&["test_x5c_leaf_forged", "test_x5c_inter"], CertificateChainError::InvalidSignature(0)),
+        (&["test_x5c_inter", "test_x5c_leaf"], CertificateChainError::KeyUsage(0)),
+        (&["test_x5c_leaf", "test_x5c_root"], CertificateChainError::IssuerMismatch(0)),
+    ];
+
+    for (chain, error) in chains {
+        let signed_token = sign_with_chain(Algorithm::RS256, "src/tests/test_private.pem", chain);
+        let result: Result<Claims, _> = crate::verify_with_x5c(&signed_token, &trust_anchors);
+        assert_eq!(result.unwrap_err(), VerifyTokenError::CertificateChain(error.clone()), "Chain {:?} should be rejected", chain);
+    }
+
+    // A trust anchor that signs the chain must itself be a valid CA
+    let signed_token = sign_with_chain(Algorithm::RS256, "src/tests/test_private.pem", &["test_x5c_anchor_leaf"]);
+    let result: Result<Claims, _> = crate::verify_with_x5c(&signed_token, &read_trust_anchors("test_x5c_anchor_expired"));
+    assert_eq!(result.unwrap_err(), VerifyTokenError::CertificateChain(CertificateChainError::Expired(1)), "An expired anchor should not be trusted");
+    let result: Result<Claims, _> = crate::verify_with_x5c(&signed_token, &read_trust_anchors("test_x5c_anchor_notca"));
+    assert_eq!(result.unwrap_err(), VerifyTokenError::CertificateChain(CertificateChainError::NotCa(1)), "An anchor that is not a CA should not be trusted");
+
+    // Another anchor with the same key that is still valid is enough
+    let mut anchors = read_trust_anchors("test_x5c_anchor_expired");
+    anchors.add_pem(&std::fs::read_to_string("src/tests/test_x5c_anchor.pem").unwrap()).unwrap();
+    assert!(crate::verify_with_x5c::<Claims>(&signed_token, &anchors).is_ok());
+
+    // A chain from a root with the same name is not trusted
+    let signed_token = sign_with_chain(Algorithm::RS256, "src/tests/test_private.pem", &["test_x5c_leaf", "test_x5c_inter"]);
+    let result: Result<Claims, _> = crate::verify_with_x5c(&signed_token, &read_trust_anchors("test_x5c_other_root"));
+    assert_eq!(result.unwrap_err(), VerifyTokenError::CertificateChain(CertificateChainError::UntrustedRoot));
+    let result: Result<Claims, _> = crate::verify_with_x5c(&signed_token, &TrustAnchors::new());
+    assert_eq!(result.unwrap_err(), VerifyTokenError::CertificateChain(CertificateChainError::UntrustedRoot));
+
+    // A token signed by another key than the leaf
+    let signed_token = sign_with_chain(Algorithm::EdDSA, "src/tests/test_ed25519_private.pem", &["test_x5c_leaf", "test_x5c_inter"]);
+    let result: Result<Claims, _> = crate::verify_with_x5c(&signed_token, &trust_anchors);
+    assert!(matches!(result.unwrap_err(), VerifyTokenError::IncompatibleKey(_)), "The token must be signed by the leaf key");
+
+    // Thumbprints of another certificate
+    for (x5t, x5t_s256, name) in [(Some("AAAA"), None, "x5t"), (None, Some("AAAA"), "x5t#S256")] {
+        let claims = Claims { exp: 100000000, sub: "123456".to_string() };
+        let private_key = std::fs::read_to_string("src/tests/test_private.pem").expect("Failed to read PEM file");
+        let mut header = Header::new(Algorithm::RS256);
+        header.x5c = Some(vec![read_x5c("test_x5c_leaf"), read_x5c("test_x5c_inter")]);
+        header.x5t = x5t.map(str::to_string);
+        header.x5t_s256 = x5t_s256.map(str::to_string);
+        let signed_token = crate::sign(&header, &claims, &private_key).unwrap();
+        let result: Result<Claims, _> = crate::verify_with_x5c(&signed_token, &trust_anchors);
+        assert_eq!(result.unwrap_err(), VerifyTokenError::CertificateChain(CertificateChainError::ThumbprintMismatch(name.to_string())));
+    }
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{decoding, encoding, model::header::{Algorithm, Header}};
+
+/// Creates an unsecured JWT (`alg: none`) with an empty signature, as defined in RFC 7519 section 6
+///
+/// **Anyone can create or modify these tokens.** Only use them where the integrity of the token
+/// is already guaranteed by something else, e.g. mTLS. They are never accepted by `verify`.
+///
+/// # Arguments
+/// * `claims` - The claims to be included in the token, which can be any serializable type.
+///
+/// # Returns
+/// * `Result<String, UnsecuredTokenError>` - The encoded token as a string, ending in a `.` as the signature is empty.
+pub fn encode_unsecured<T: Serialize>(claims: &T) -> Result<String, UnsecuredTokenError> {
+    // Encode the header and claims
+    let encoded_header = encoding::header::encode(&Header::new(Algorithm::None)).map_err(|_| UnsecuredTokenError::EncodingHeader)?;
+    let encoded_claims = encoding::claims::encode(claims).map_err(|_| UnsecuredTokenError::EncodingClaims)?;
+
+    // The signature is the empty string
+    Ok(format!("{}.{}.", encoded_header, encoded_claims))
+}
+
+/// Reads the claims from an unsecured JWT (`alg: none`) **without any verification**
+///
+/// Only tokens with `alg: none` and an empty signature are accepted, signed tokens must go through `verify`.
+///
+/// # Arguments
+/// * `token` - A string representing the unsecured token (header.payload.)
+/// * `T` - The type to deserialize the claims into
+///
+/// # Returns
+/// * `Result<T, UnsecuredTokenError>` - Returns the claims, or an error if the token is not a valid unsecured token
+pub fn decode_unsecured<T: Clone + for<'a> Deserialize<'a>>(token: &str) -> Result<T, UnsecuredTokenError> {
+    // Split the token into parts
+    let split_token = token.split('.').collect::<Vec<&str>>();
+    if split_token.len() != 3 {
+        return Err(UnsecuredTokenError::InvalidFormat);
+    }
+
+    // The header must say the token is unsecured
+    let header = decoding::header::decode(split_token[0]).map_err(|_| UnsecuredTokenError::DeserializingHeader)?;
+    if header.alg != Algorithm::None {
+        return Err(UnsecuredTokenError::NotUnsecured(header.alg.to_string()));
+    }
+
+    // And the signature must be empty
+    if !split_token[2].is_empty() {
+        return Err(UnsecuredTokenError::NonEmptySignature);
+    }
+
+    // Return the claims
+    let claims: T = decoding::claims::decode(split_token[1]).map_err(|_| UnsecuredTokenError::DeserializingClaims)?;
+    Ok(claims)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Type returned if there is an error creating or reading an unsecured token
+///
+/// # Variants
+/// * `EncodingHeader` - There was an error encoding the header
+/// * `EncodingClaims` - There was an error encoding the claims
+/// * `InvalidFormat` - The token does not have 3 parts
+/// * `DeserializingHeader` - There was an error deserializing the header
+/// * `NotUnsecured` - The token uses a real algorithm and must be verified
+/// * `NonEmptySignature` - The token says it is unsecured but has a signature
+/// * `DeserializingClaims` - There was an error deserializing the claims into the given type
+pub enum UnsecuredTokenError {
+    /// There was an error encoding the header
+    EncodingHeader,
+    /// There was an error encoding the claims
+    EncodingClaims,
+    /// The token does not have 3 parts
+    InvalidFormat,
+    /// There was an error deserializing the header
+    DeserializingHeader,
+    /// The token uses a real algorithm and must be verified
+    NotUnsecured(String),
+    /// The token says it is unsecured but has a signature
+    NonEmptySignature,
+    /// There was an error deserializing the claims into the given type
+    DeserializingClaims,
+}
\ No newline at end of file
@@ -0,0 +1,79 @@
+use p256::ecdsa::signature::Verifier;
+
+use crate::keys::{check_curve, EcCurve};
+use crate::keys::decoding_key::{DecodingKey, DecodingKeyKind};
+use crate::model::header::Algorithm;
+
+use super::{key_error, verify_split_with_key, VerifyingTokenError};
+
+/// Verify a token using ECDSA and an EC public key
+///
+/// The signature must be the fixed width `R || S` encoding required by RFC 7518.
+///
+/// # Arguments
+/// * `token` - A slice of strings representing the token parts (header, payload, signature)
+/// * `public_key` - A string representing the EC public key in PEM format
+/// * `algorithm` - The ECDSA algorithm to use, one of `ES256` (P-256), `ES384` (P-384), `ES512` (P-521) or `ES256K` (secp256k1)
+///
+/// # Returns
+/// * `bool` - Returns true if the token signature is valid, false otherwise
+pub fn verify(token: &[&str], public_key: &str, algorithm: &Algorithm) -> Result<bool, VerifyingTokenError> {
+    let key = DecodingKey::from_ec_pem(public_key).map_err(key_error)?;
+    verify_split_with_key(token, &key, algorithm)
+}
+
+
+/// Verify the signature of a signing input using ECDSA and an already parsed EC public key
+///
+/// # Arguments
+/// * `data` - The signing input, the base64 encoded header and payload joined with a dot
+/// * `signature` - The decoded signature
+/// * `key` - The EC public key, which must be for the curve of the algorithm
+/// * `algorithm` - The ECDSA algorithm to use, one of `ES256` (P-256), `ES384` (P-384), `ES512` (P-521) or `ES256K` (secp256k1)
+///
+/// # Returns
+/// * `bool` - Returns true if the signature is valid, false otherwise
+pub fn verify_signature(data: &[u8], signature: &[u8], key: &DecodingKey, algorithm: &Algorithm) -> Result<bool, VerifyingTokenError> {
+    if EcCurve::for_algorithm(algorithm).is_none() {
+        return Err(VerifyingTokenError::Other(format!("{} is not an ECDSA algorithm", algorithm)));
+    }
+
+    // Check the key is for the curve of the algorithm and verify the signature
+    // The key and signature types differ per curve, so this must be a match statement
+    let incompatible = |err: crate::keys::KeyError| VerifyingTokenError::IncompatibleKey(err.to_string());
+    match key.kind.as_ref() {
+        DecodingKeyKind::P256(verifying_key) => {
+            check_curve(EcCurve::P256, algorithm).map_err(incompatible)?;
+            match p256::ecdsa::Signature::from_slice(signature) {
+                Ok(signature) => Ok(verifying_key.verify(data, &signature).is_ok()),
+                Err(_) => Ok(false),
+            }
+        }
+        DecodingKeyKind::P384(verifying_key) => {
+            check_curve(EcCurve::P384, algorithm).map_err(incompatible)?;
+            match p384::ecdsa::Signature::from_slice(signature) {
+                Ok(signature) => Ok(verifying_key.verify(data, &signature).is_ok()),
+                Err(_) => Ok(false),
+            }
+        }
+        DecodingKeyKind::P521(verifying_key) => {
+            check_curve(EcCurve::P521, algorithm).map_err(incompatible)?;
+            match p521::ecdsa::Signature::from_slice(signature) {
+                Ok(signature) => Ok(verifying_key.verify(data, &signature).is_ok()),
+                Err(_) => Ok(false),
+            }
+        }
+        DecodingKeyKind::Secp256k1(verifying_key) => {
+            check_curve(EcCurve::Secp256k1, algorithm).map_err(incompatible)?;
+            match k256::ecdsa::Signature::from_slice(signature) {
+                // RFC 8812 does not require low S values, but k256 only verifies those
+                Ok(signature) => {
+                    let signature = signature.normalize_s().unwrap_or(signature);
+                    Ok(verifying_key.verify(data, &signature).is_ok())
+                }
+                Err(_) => Ok(false),
+            }
+        }
+        _ => Err(VerifyingTokenError::IncompatibleKey(format!("{} requires an EC key", algorithm))),
+    }
+}
@@ -0,0 +1,52 @@
+use ed25519_dalek::Verifier;
+
+use crate::keys::decoding_key::{DecodingKey, DecodingKeyKind};
+use crate::model::header::Algorithm;
+
+use super::{key_error, verify_split_with_key, VerifyingTokenError};
+
+/// Verify a token using EdDSA, as defined in RFC 8037
+///
+/// The curve, Ed25519 or Ed448, is chosen by the key.
+///
+/// # Arguments
+/// * `token` - A slice of strings representing the token parts (header, payload, signature)
+/// * `public_key` - A string representing the public key, either in PEM format or as an OKP JWK
+/// * `algorithm` - The algorithm from the token header, which must be `EdDSA`
+///
+/// # Returns
+/// * `bool` - Returns true if the token signature is valid, false otherwise
+pub fn verify(token: &[&str], public_key: &str, algorithm: &Algorithm) -> Result<bool, VerifyingTokenError> {
+    let key = DecodingKey::for_algorithm(public_key, &Algorithm::EdDSA).map_err(key_error)?;
+    verify_split_with_key(token, &key, algorithm)
+}
+
+
+/// Verify the signature of a signing input using EdDSA and an already parsed public key
+///
+/// # Arguments
+/// * `data` - The signing input, the base64 encoded header and payload joined with a dot
+/// * `signature` - The decoded signature
+/// * `key` - The Ed25519 or Ed448 public key
+/// * `algorithm` - The algorithm from the token header, which must be `EdDSA`
+///
+/// # Returns
+/// * `bool` - Returns true if the signature is valid, false otherwise
+pub fn verify_signature(data: &[u8], signature: &[u8], key: &DecodingKey, algorithm: &Algorithm) -> Result<bool, VerifyingTokenError> {
+    if *algorithm != Algorithm::EdDSA {
+        return Err(VerifyingTokenError::Other(format!("{} is not an EdDSA algorithm", algorithm)));
+    }
+
+    // Verify the signature with the curve of the key
+    match key.kind.as_ref() {
+        DecodingKeyKind::Ed25519(verifying_key) => match ed25519_dalek::Signature::from_slice(signature) {
+            Ok(signature) => Ok(verifying_key.verify(data, &signature).is_ok()),
+            Err(_) => Ok(false),
+        },
+        DecodingKeyKind::Ed448(verifying_key) => match ed448_goldilocks_plus::Signature::try_from(signature) {
+            Ok(signature) => Ok(verifying_key.verify_raw(&signature, data).is_ok()),
+            Err(_) => Ok(false),
+        },
+        _ => Err(VerifyingTokenError::IncompatibleKey(format!("{} requires an Ed25519 or Ed448 key", algorithm))),
+    }
+}
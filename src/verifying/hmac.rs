@@ -0,0 +1,64 @@
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha384, Sha512};
+
+use crate::keys::decoding_key::{DecodingKey, DecodingKeyKind};
+use crate::keys::is_key_document;
+use crate::keys::policy::check_hmac_length;
+use crate::model::header::Algorithm;
+
+use super::{verify_split_with_key, VerifyingTokenError};
+
+/// Verify a token using HMAC and a shared secret
+///
+/// The signature is compared in constant time. A PEM or JWK key is never used as the secret, as the algorithm
+/// comes from the token and a public key would let anyone forge tokens, `IncompatibleKey` is returned instead.
+///
+/// # Arguments
+/// * `token` - A slice of strings representing the token parts (header, payload, signature)
+/// * `secret` - A string representing the shared secret
+/// * `algorithm` - The HMAC algorithm to use, one of `HS256`, `HS384` or `HS512`
+///
+/// # Returns
+/// * `bool` - Returns true if the token signature is valid, false otherwise
+pub fn verify(token: &[&str], secret: &str, algorithm: &Algorithm) -> Result<bool, VerifyingTokenError> {
+    if is_key_document(secret) {
+        return Err(VerifyingTokenError::IncompatibleKey(format!("{} requires a shared secret, not a PEM or JWK key", algorithm)));
+    }
+    let key = DecodingKey::from_secret(secret.as_bytes()).map_err(|err| VerifyingTokenError::WeakKey(err.to_string()))?;
+    verify_split_with_key(token, &key, algorithm)
+}
+
+
+/// Verify the signature of a signing input using HMAC and a shared secret key
+///
+/// # Arguments
+/// * `data` - The signing input, the base64 encoded header and payload joined with a dot
+/// * `signature` - The decoded signature
+/// * `key` - The shared secret key
+/// * `algorithm` - The HMAC algorithm to use, one of `HS256`, `HS384` or `HS512`
+///
+/// # Returns
+/// * `bool` - Returns true if the signature is valid, false otherwise
+pub fn verify_signature(data: &[u8], signature: &[u8], key: &DecodingKey, algorithm: &Algorithm) -> Result<bool, VerifyingTokenError> {
+    let DecodingKeyKind::Hmac(secret) = key.kind.as_ref() else {
+        return Err(VerifyingTokenError::IncompatibleKey(format!("{} requires a shared secret", algorithm)));
+    };
+    if key.hmac_hash_length {
+        check_hmac_length(secret, algorithm).map_err(VerifyingTokenError::WeakKey)?;
+    }
+
+    // Match the algorithm to pick the hash function and verify the signature
+    match algorithm {
+        Algorithm::HS256 => compare::<Hmac<Sha256>>(secret, data, signature),
+        Algorithm::HS384 => compare::<Hmac<Sha384>>(secret, data, signature),
+        Algorithm::HS512 => compare::<Hmac<Sha512>>(secret, data, signature),
+        _ => Err(VerifyingTokenError::Other(format!("{} is not an HMAC algorithm", algorithm))),
+    }
+}
+
+/// Computes the MAC of `data` and compares it to `signature` in constant time
+fn compare<M: Mac + hmac::digest::KeyInit>(secret: &[u8], data: &[u8], signature: &[u8]) -> Result<bool, VerifyingTokenError> {
+    let mut mac = <M as Mac>::new_from_slice(secret).map_err(|_| VerifyingTokenError::VerifyingKey)?;
+    mac.update(data);
+    Ok(mac.verify_slice(signature).is_ok())
+}
@@ -1,76 +1,410 @@
+pub mod ecdsa;
+pub mod eddsa;
+pub mod hmac;
 pub mod rsa;
+pub mod verifier;
 
+use std::time::SystemTime;
+
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 
-use crate::model::header::Algorithm;
+use crate::keys::certificate::{validate_chain, CertificateChainError, TrustAnchors};
+use crate::keys::jwk::matches_algorithm;
+use crate::keys::jwks_client::{JwksClient, JwksError};
+use crate::keys::key_ring::KeyRing;
+use crate::keys::key_set::DecodingKeySet;
+use crate::keys::KeyError;
+use crate::model::header::{Algorithm, Header};
 use crate::decoding;
 
+use self::verifier::{AsyncVerifier, Verifier};
+
 /// Verify a token using the given algorithm and (public/verifying) key
 /// 
 /// Does not check any of the claims, e.g. exp. This is to be done by the caller
 /// 
 /// # Arguments
 /// * `signed_token` - A string representing the signed token (header.payload.signature)
-/// * `key_from_pem` - A string representing the public key in PEM format
+/// * `key_from_pem` - A string representing the public key in PEM format, or the shared secret for HMAC algorithms.
+///   A PEM or JWK key is never used as a shared secret, so an `HS*` token is rejected with `IncompatibleKey` rather than
+///   verified with a public key that anyone could have signed it with
 /// * `T` - The type to deserialize the claims into
 /// 
 /// # Returns
 /// * `Result<T, VerifyingTokenError>` - Returns the claims if the token is valid, or an error if it is not
 /// 
 /// # Example
-/// ```rust
-/// use crate::verifying::verify;
-/// use crate::model::claims::Claims;
+/// ```rust,no_run
+/// use super_simple_jwt::verify;
+/// use serde::{Deserialize, Serialize};
+/// 
+/// #[derive(Serialize, Deserialize, Clone)]
+/// struct Claims {
+///     exp: usize,
+///     sub: String,
+/// }
 /// 
 /// let token = "pretend_this.is_a.valid_signed_token";
 /// let public_key = "pretend_this_is_a_valid_public_key_from_a_pem_file";
 /// let claims: Claims = verify(token, public_key).unwrap();
 /// ```
 pub fn verify<T: Clone + Serialize + for<'a> Deserialize<'a>>(signed_token: &str, key_from_pem: &str) -> Result<T, VerifyingTokenError> {
-    #[allow(unused_assignments)]
-    let mut verified: bool = false;
+    verify_token(signed_token, key_from_pem, false)
+}
 
+/// Verify a token, also accepting `RS256`/`RS512` tokens that were signed with RSASSA-PSS
+///
+/// Earlier versions of this crate labelled PSS signatures as `RS256`/`RS512`. Tokens issued by those versions
+/// are rejected by `verify`, this function accepts them as well as correctly signed tokens.
+/// Only use this while tokens from older versions are still in circulation.
+///
+/// # Arguments
+/// * `signed_token` - A string representing the signed token (header.payload.signature)
+/// * `key_from_pem` - A string representing the public key in PEM format, or the shared secret for HMAC algorithms.
+///   A PEM or JWK key is never used as a shared secret, so an `HS*` token is rejected with `IncompatibleKey` rather than
+///   verified with a public key that anyone could have signed it with
+/// * `T` - The type to deserialize the claims into
+///
+/// # Returns
+/// * `Result<T, VerifyingTokenError>` - Returns the claims if the token is valid, or an error if it is not
+pub fn verify_legacy<T: Clone + Serialize + for<'a> Deserialize<'a>>(signed_token: &str, key_from_pem: &str) -> Result<T, VerifyingTokenError> {
+    verify_token(signed_token, key_from_pem, true)
+}
+
+/// Verify a token using an already parsed (public/verifying) key, or any other `Verifier`
+///
+/// The key is read once with `DecodingKey` and can be reused for any number of tokens.
+/// The key must match the algorithm in the token header, e.g. an RSA key for `RS256`, otherwise `IncompatibleKey` is returned.
+/// A `Verifier` can also verify with a key that is held elsewhere, e.g. in a hardware security module or a cloud key service.
+/// Does not check any of the claims, e.g. exp. This is to be done by the caller
+///
+/// # Arguments
+/// * `signed_token` - A string representing the signed token (header.payload.signature)
+/// * `key` - The key used to verify the token
+/// * `T` - The type to deserialize the claims into
+///
+/// # Returns
+/// * `Result<T, VerifyingTokenError>` - Returns the claims if the token is valid, or an error if it is not
+pub fn verify_with_key<T: Clone + Serialize + for<'a> Deserialize<'a>>(signed_token: &str, key: &(impl Verifier + ?Sized)) -> Result<T, VerifyingTokenError> {
+    // Split the token into parts
+    let split_token = signed_token.split('.').collect::<Vec<&str>>(); 
+
+    // Read the algorithm from the token
+    let alg = read_header(split_token[0])?.alg;
+
+    let verified = verify_split_with_key(&split_token, key, &alg)?;
+    decode_verified(&split_token, verified)
+}
+
+/// Verify a token with an `AsyncVerifier`, e.g. a cloud key service that is called over the network
+///
+/// Behaves as `verify_with_key` once the signature is checked. Any `Verifier`, including `DecodingKey`, can also be used.
+/// Does not check any of the claims, e.g. exp. This is to be done by the caller
+///
+/// # Arguments
+/// * `signed_token` - A string representing the signed token (header.payload.signature)
+/// * `verifier` - The verifier that checks the signature
+/// * `T` - The type to deserialize the claims into
+///
+/// # Returns
+/// * `Result<T, VerifyingTokenError>` - Returns the claims if the token is valid, or an error if it is not
+pub async fn verify_async<T: Clone + Serialize + for<'a> Deserialize<'a>>(signed_token: &str, verifier: &(impl AsyncVerifier + ?Sized)) -> Result<T, VerifyingTokenError> {
+    // Split the token into parts
+    let split_token = signed_token.split('.').collect::<Vec<&str>>();
+
+    // Read the algorithm from the token
+    let alg = read_header(split_token[0])?.alg;
+
+    // Unsecured tokens are never verified, even with the `dangerous-unsecured` feature
+    if alg == Algorithm::None {
+        return Err(VerifyingTokenError::UnsecuredToken);
+    }
+
+    let (data, signature) = split_signature(&split_token)?;
+    let verified = AsyncVerifier::verify(verifier, data.as_bytes(), &signature, &alg).await?;
+    decode_verified(&split_token, verified)
+}
+
+/// Verify a token with the key picked from the keys of a JWK Set
+///
+/// If the token header has a `kid`, the key with that ID is used and `UnknownKeyId` is returned if there is none.
+/// Otherwise every key whose `alg`, or key type and curve when it has no `alg`, matches the algorithm of the token is tried.
+/// Keys whose `use` or `key_ops` do not allow verifying are never used.
+/// Does not check any of the claims, e.g. exp. This is to be done by the caller
+///
+/// # Arguments
+/// * `signed_token` - A string representing the signed token (header.payload.signature)
+/// * `keys` - The keys the token may be signed with, read from a JWK Set
+/// * `T` - The type to deserialize the claims into
+///
+/// # Returns
+/// * `Result<T, VerifyingTokenError>` - Returns the claims if the token is valid, or an error if it is not
+pub fn verify_with_jwks<T: Clone + Serialize + for<'a> Deserialize<'a>>(signed_token: &str, keys: &DecodingKeySet) -> Result<T, VerifyingTokenError> {
+    // Split the token into parts
+    let split_token = signed_token.split('.').collect::<Vec<&str>>();
+
+    // Read the algorithm and key ID from the token
+    let header = read_header(split_token[0])?;
+
+    // Pick the keys to try, by key ID if the token has one
+    let candidates: Vec<_> = match &header.kid {
+        Some(kid) => {
+            let entries: Vec<_> = keys.entries().iter().filter(|entry| entry.jwk.kid.as_deref() == Some(kid.as_str())).collect();
+            if entries.is_empty() {
+                return Err(VerifyingTokenError::UnknownKeyId(kid.clone()));
+            }
+            // Several keys can share an ID, e.g. the same key for different algorithms
+            entries.into_iter().filter(|entry| matches_algorithm(&entry.jwk, &header.alg)).collect()
+        }
+        None => keys.entries().iter().filter(|entry| matches_algorithm(&entry.jwk, &header.alg)).collect(),
+    };
+
+    // Return the claims with the first key that verifies the token, or the error of the last key tried
+    let mut last_error = VerifyingTokenError::NoMatchingKey;
+    for entry in candidates {
+        let result = match &entry.key {
+            Some(key) => verify_split_with_key(&split_token, key, &header.alg),
+            None => Err(VerifyingTokenError::IncompatibleKey("JWK 'use' or 'key_ops' does not allow verifying".to_string())),
+        };
+        match result {
+            Ok(true) => return decode_verified(&split_token, true),
+            Ok(false) => last_error = VerifyingTokenError::InvalidSignature,
+            Err(err) => last_error = err,
+        }
+    }
+    Err(last_error)
+}
+
+/// Verify a token with the keys of a remote JWK Set, fetched and cached by a `JwksClient`
+///
+/// If the token has a `kid` that is not in the cached keys, the keys are fetched again first, unless they were fetched within the minimum refresh interval.
+/// The key is then picked as by `verify_with_jwks`.
+/// Does not check any of the claims, e.g. exp. This is to be done by the caller
+///
+/// # Arguments
+/// * `signed_token` - A string representing the signed token (header.payload.signature)
+/// * `client` - The client of the JWK Set the token may be signed with
+/// * `T` - The type to deserialize the claims into
+///
+/// # Returns
+/// * `Result<T, VerifyingTokenError>` - Returns the claims if the token is valid, `FetchingKeys` if no keys were ever fetched, or an error if it is not valid
+pub fn verify_with_jwks_client<T: Clone + Serialize + for<'a> Deserialize<'a>>(signed_token: &str, client: &JwksClient) -> Result<T, VerifyingTokenError> {
+    // Read the key ID from the token
+    let header_part = signed_token.split('.').next().unwrap_or_default();
+    let header = read_header(header_part)?;
+
+    // A key ID that is not in the set may be a key the provider has just added
+    let mut keys = client.keys().map_err(VerifyingTokenError::FetchingKeys)?;
+    if header.kid.as_ref().is_some_and(|kid| keys.find(kid).next().is_none()) {
+        keys = client.refresh().map_err(VerifyingTokenError::FetchingKeys)?;
+    }
+    verify_with_jwks(signed_token, &keys)
+}
+
+/// Verify a token with the keys of a key ring, which accepts tokens from the active key and from older keys during rotation
+///
+/// If the token header has a `kid`, the key with that ID is used and `UnknownKeyId` is returned if there is none,
+/// or `KeyNotValid` if the key is outside its not-before/not-after window.
+/// Otherwise every key that is currently valid is tried.
+/// Does not check any of the claims, e.g. exp. This is to be done by the caller
+///
+/// # Arguments
+/// * `signed_token` - A string representing the signed token (header.payload.signature)
+/// * `key_ring` - The keys the token may be signed with
+/// * `T` - The type to deserialize the claims into
+///
+/// # Returns
+/// * `Result<T, VerifyingTokenError>` - Returns the claims if the token is valid, or an error if it is not
+pub fn verify_with_key_ring<T: Clone + Serialize + for<'a> Deserialize<'a>>(signed_token: &str, key_ring: &KeyRing) -> Result<T, VerifyingTokenError> {
+    // Split the token into parts
+    let split_token = signed_token.split('.').collect::<Vec<&str>>();
+
+    // Read the algorithm and key ID from the token
+    let header = read_header(split_token[0])?;
+    let now = SystemTime::now();
+
+    // Pick the keys to try, by key ID if the token has one
+    let candidates: Vec<_> = match &header.kid {
+        Some(kid) => {
+            let entry = key_ring.get(kid).ok_or_else(|| VerifyingTokenError::UnknownKeyId(kid.clone()))?;
+            if !entry.is_valid_at(now) {
+                return Err(VerifyingTokenError::KeyNotValid(kid.clone()));
+            }
+            vec![entry]
+        }
+        None => key_ring.entries().iter().filter(|entry| entry.is_valid_at(now)).collect(),
+    };
+
+    // Return the claims with the first key that verifies the token, skipping keys of the wrong type when there is no key ID
+    let mut last_error = VerifyingTokenError::NoMatchingKey;
+    for entry in candidates {
+        match verify_split_with_key(&split_token, &entry.key, &header.alg) {
+            Ok(true) => return decode_verified(&split_token, true),
+            Ok(false) => last_error = VerifyingTokenError::InvalidSignature,
+            Err(VerifyingTokenError::IncompatibleKey(_)) if header.kid.is_none() => {},
+            Err(err) => last_error = err,
+        }
+    }
+    Err(last_error)
+}
+
+/// Verifies the signature of a split token with a key, which must be of the type the algorithm requires
+pub(crate) fn verify_split_with_key<V: Verifier + ?Sized>(split_token: &[&str], key: &V, alg: &Algorithm) -> Result<bool, VerifyingTokenError> {
+    // Unsecured tokens are never verified, even with the `dangerous-unsecured` feature
+    if *alg == Algorithm::None {
+        return Err(VerifyingTokenError::UnsecuredToken);
+    }
+
+    let (data, signature) = split_signature(split_token)?;
+    key.verify(data.as_bytes(), &signature, alg)
+}
+
+/// Converts the error of a key that could not be read, keeping the reason
+pub(crate) fn key_error(err: KeyError) -> VerifyingTokenError {
+    match err {
+        KeyError::IncompatibleKey(message) => VerifyingTokenError::IncompatibleKey(message),
+        KeyError::WeakKey(message) => VerifyingTokenError::WeakKey(message),
+        err => VerifyingTokenError::Key(err),
+    }
+}
+
+/// Splits a token into its signing input, the header and payload joined with a dot, and its decoded signature
+pub(crate) fn split_signature(split_token: &[&str]) -> Result<(String, Vec<u8>), VerifyingTokenError> {
+    if split_token.len() != 3 {
+        return Err(VerifyingTokenError::Other("Token must have 3 parts".to_string()));
+    }
+    let signature = decoding::DECODING_ENGINE.decode(split_token[2]).map_err(|_| VerifyingTokenError::InvalidSignature)?;
+    Ok((format!("{}.{}", split_token[0], split_token[1]), signature))
+}
+
+/// Decodes the header of a token, rejecting it if it lists any `crit` extension parameters, as none are supported
+pub(crate) fn read_header(header_part: &str) -> Result<Header, VerifyingTokenError> {
+    let header = decoding::header::decode(header_part).map_err(|_| VerifyingTokenError::DeserializingHeader)?;
+
+    // RFC 7515 requires tokens with critical extensions the recipient does not understand to be rejected
+    if let Some(crit) = &header.crit {
+        return Err(VerifyingTokenError::CriticalHeader(crit.join(", ")));
+    }
+    Ok(header)
+}
+
+/// Verify a token with the leaf certificate of the `x5c` certificate chain in its header
+///
+/// The chain is validated offline against the trust anchors: every certificate must be within its validity window,
+/// each certificate must be signed by the next one, which must be a CA, and the chain must end at or be signed by a trust anchor, which is then held to the same checks as a CA in the chain.
+/// The `x5t` and `x5t#S256` thumbprints are checked against the leaf certificate when present.
+/// Revocation is not checked. Does not check any of the claims, e.g. exp. This is to be done by the caller
+///
+/// # Arguments
+/// * `signed_token` - A string representing the signed token (header.payload.signature)
+/// * `trust_anchors` - The certificates trusted to issue the chain
+/// * `T` - The type to deserialize the claims into
+///
+/// # Returns
+/// * `Result<T, VerifyingTokenError>` - Returns the claims if the token is valid, or `CertificateChain` if the chain cannot be trusted
+pub fn verify_with_x5c<T: Clone + Serialize + for<'a> Deserialize<'a>>(signed_token: &str, trust_anchors: &TrustAnchors) -> Result<T, VerifyingTokenError> {
+    // Read the full header, which carries the chain
+    let header_part = signed_token.split('.').next().unwrap_or_default();
+    let header = read_header(header_part)?;
+
+    // Validate the chain and verify the token with the key of the leaf certificate
+    let key = validate_chain(&header, trust_anchors, SystemTime::now()).map_err(VerifyingTokenError::CertificateChain)?;
+    verify_with_key(signed_token, &key)
+}
+
+/// Verifies the token and decodes the claims, optionally accepting legacy PSS signatures for `RS256`/`RS512`
+fn verify_token<T: Clone + Serialize + for<'a> Deserialize<'a>>(signed_token: &str, key_from_pem: &str, accept_legacy_pss: bool) -> Result<T, VerifyingTokenError> {
     // Split the token into parts
     let split_token = signed_token.split('.').collect::<Vec<&str>>(); 
     
     // Read the algorithm from the token
-    let alg = decoding::header::decode(&split_token[0]).unwrap().alg; // This should be updated for better error handling
+    let alg = read_header(split_token[0])?.alg;
 
     // Verify the token using the algorithm
-    match alg {
-        Algorithm::RS256 => verified = rsa::verify(&split_token, key_from_pem, &alg)?,
-        Algorithm::RS512 => verified = rsa::verify(&split_token, key_from_pem, &alg)?,
+    let mut verified = match alg {
+        Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512 => rsa::verify(&split_token, key_from_pem, &alg)?,
+        Algorithm::ES256 | Algorithm::ES384 | Algorithm::ES512 | Algorithm::ES256K => ecdsa::verify(&split_token, key_from_pem, &alg)?,
+        Algorithm::EdDSA => eddsa::verify(&split_token, key_from_pem, &alg)?,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => hmac::verify(&split_token, key_from_pem, &alg)?,
+        // Unsecured tokens are never verified, even with the `dangerous-unsecured` feature
+        Algorithm::None => return Err(VerifyingTokenError::UnsecuredToken),
+    };
+
+    // Fall back to the legacy PSS signature if requested
+    if !verified && accept_legacy_pss && matches!(alg, Algorithm::RS256 | Algorithm::RS512) {
+        verified = rsa::verify_legacy_pss(&split_token, key_from_pem, &alg)?;
     }
 
+    decode_verified(&split_token, verified)
+}
+
+/// Decodes the claims of a token, or returns an error if the signature was not verified
+fn decode_verified<T: Clone + Serialize + for<'a> Deserialize<'a>>(split_token: &[&str], verified: bool) -> Result<T, VerifyingTokenError> {
     // Return an error if not verified
     if !verified {
         return Err(VerifyingTokenError::InvalidSignature);
     };
 
     // Return the claims
-    let claims: T = decoding::claims::decode(&split_token[1]).map_err(|_| VerifyingTokenError::DeserializingClaims)?;
+    let claims: T = decoding::claims::decode(split_token[1]).map_err(|_| VerifyingTokenError::DeserializingClaims)?;
     Ok(claims)
-
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 /// Type returned if there is an error when verifying a token
+///
+/// The variants are the same with or without features, and more may be added, so matches need a wildcard arm.
 /// 
 /// # Variants
 /// * `InvalidSignature` - The signature is invalid
 /// * `DeserializingHeader` - There was an error deserializing the header
+/// * `CriticalHeader` - The header lists `crit` extension parameters, which are not supported
 /// * `DeserializingClaims` - There was an error deserializing the claims into the given type
 /// * `VerifyingKey` - The public key is invalid
+/// * `Key` - The key could not be read, e.g. a private key given where a public key is expected, or an expired certificate
+/// * `IncompatibleKey` - The key cannot be used with the algorithm of the token
+/// * `CertificateChain` - The `x5c` certificate chain of the token cannot be trusted
+/// * `UnknownKeyId` - No key in the key set has the `kid` of the token
+/// * `NoMatchingKey` - No key in the key set with the `kid` of the token, or any key if it has none, can be used with its algorithm
+/// * `KeyNotValid` - The key with the `kid` of the token is outside its not-before/not-after window
+/// * `FetchingKeys` - The remote JWK Set could not be fetched, and no keys were fetched before
+/// * `WeakKey` - The key is weaker than the `KeyPolicy` it was read with, e.g. an HMAC secret shorter than the hash output
+/// * `Verifier` - A `Verifier` backend failed, e.g. a key service that could not be reached
+/// * `UnsecuredToken` - The token is unsecured (`alg: none`), which is never accepted
 /// * `Other` - There was an unknown error
 pub enum VerifyingTokenError {
     /// The signature is invalid
     InvalidSignature,
     /// There was an error deserializing the header
     DeserializingHeader,
+    /// The header lists `crit` extension parameters, which are not supported
+    CriticalHeader(String),
     /// There was an error deserializing the claims into the given type
     DeserializingClaims,
     /// The public key is invalid
     VerifyingKey,
+    /// The key could not be read, the `KeyError` says why, e.g. the format that was found instead of a public key
+    Key(KeyError),
+    /// The key cannot be used with the algorithm of the token, e.g. a shared secret for an `RS256` token
+    IncompatibleKey(String),
+    /// The `x5c` certificate chain of the token cannot be trusted
+    CertificateChain(CertificateChainError),
+    /// No key in the key set has the `kid` of the token
+    UnknownKeyId(String),
+    /// No key in the key set with the `kid` of the token, or any key if it has none, can be used with its algorithm
+    NoMatchingKey,
+    /// The key with the `kid` of the token is outside its not-before/not-after window
+    KeyNotValid(String),
+    /// The remote JWK Set could not be fetched, and no keys were fetched before
+    FetchingKeys(JwksError),
+    /// The key is weaker than the `KeyPolicy` it was read with, e.g. an HMAC secret shorter than the hash output
+    WeakKey(String),
+    /// A `Verifier` backend failed, e.g. a key service that could not be reached
+    Verifier(String),
+    /// The token is unsecured (`alg: none`), these are never accepted by `verify`
+    UnsecuredToken,
     /// There was an unkown error
     Other(String),
 }
\ No newline at end of file
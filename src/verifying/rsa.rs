@@ -1,53 +1,97 @@
-use base64::Engine;
-use rsa::pkcs8::DecodePublicKey;
-use rsa::pss::Signature;
-use rsa::sha2::Sha512;
-use rsa::signature::Verifier;
-use rsa::RsaPublicKey;
-use rsa::{pss::VerifyingKey, sha2::Sha256};
+use rsa::sha2::{Digest, Sha256, Sha384, Sha512};
+use rsa::{Pkcs1v15Sign, Pss, RsaPublicKey};
 
+use crate::keys::decoding_key::{DecodingKey, DecodingKeyKind};
 use crate::model::header::Algorithm;
 
-use super::VerifyingTokenError;
+use super::{key_error, split_signature, verify_split_with_key, VerifyingTokenError};
 
-/// Verify a token using RSA256 and an RSA public key
-/// 
+/// Verify a token using RSA and an RSA public key
+///
+/// The `RS*` algorithms use RSASSA-PKCS1-v1_5 and the `PS*` algorithms use RSASSA-PSS, as defined in RFC 7518.
+///
 /// # Arguments
 /// * `token` - A slice of strings representing the token parts (header, payload, signature)
 /// * `public_key` - A string representing the RSA public key in PEM format
-/// 
+/// * `algorithm` - The RSA algorithm to use, one of `RS256`, `RS384`, `RS512`, `PS256`, `PS384` or `PS512`
+///
 /// # Returns
 /// * `bool` - Returns true if the token signature is valid, false otherwise
 pub fn verify(token: &[&str], public_key: &str, algorithm: &Algorithm) -> Result<bool, VerifyingTokenError> {
-    if token.len() != 3 {
-        return Err(VerifyingTokenError::Other("Token must have 3 parts".to_string()));
-    }
+    let key = DecodingKey::from_rsa_pem(public_key).map_err(key_error)?;
+    verify_split_with_key(token, &key, algorithm)
+}
 
-    // Seperate the token parts
-    let header = token[0];
-    let payload = token[1];
-    let signature = token[2];
 
-    let decoded_signature = crate::decoding::DECODING_ENGINE.decode(signature).unwrap();
-    let decoded_signature = decoded_signature.as_slice();
+/// Verify the signature of a signing input using RSA and an already parsed RSA public key
+///
+/// # Arguments
+/// * `data` - The signing input, the base64 encoded header and payload joined with a dot
+/// * `signature` - The decoded signature
+/// * `key` - The RSA public key
+/// * `algorithm` - The RSA algorithm to use, one of `RS256`, `RS384`, `RS512`, `PS256`, `PS384` or `PS512`
+///
+/// # Returns
+/// * `bool` - Returns true if the signature is valid, false otherwise
+pub fn verify_signature(data: &[u8], signature: &[u8], key: &DecodingKey, algorithm: &Algorithm) -> Result<bool, VerifyingTokenError> {
+    let rsa_pub = rsa_key(key, algorithm)?;
 
-    // Get the public key
-    let rsa_pub = RsaPublicKey::from_public_key_pem(public_key).map_err(|_| VerifyingTokenError::VerifyingKey)?;
-    let data = format!("{}.{}", header, payload);
+    // Match the algorithm to pick the padding scheme and hash function and verify the signature
+    match algorithm {
+        Algorithm::RS256 => Ok(verify_pkcs1v15::<Sha256>(rsa_pub, data, signature)),
+        Algorithm::RS384 => Ok(verify_pkcs1v15::<Sha384>(rsa_pub, data, signature)),
+        Algorithm::RS512 => Ok(verify_pkcs1v15::<Sha512>(rsa_pub, data, signature)),
+        Algorithm::PS256 => Ok(verify_pss::<Sha256>(rsa_pub, data, signature)),
+        Algorithm::PS384 => Ok(verify_pss::<Sha384>(rsa_pub, data, signature)),
+        Algorithm::PS512 => Ok(verify_pss::<Sha512>(rsa_pub, data, signature)),
+        _ => Err(VerifyingTokenError::Other(format!("{} is not an RSA algorithm", algorithm))),
+    }
+}
+
+/// Verify a token labelled `RS256` or `RS512` that was signed with RSASSA-PSS
+///
+/// Earlier versions of this crate signed `RS*` tokens using PSS instead of PKCS1-v1_5.
+/// This allows those tokens to still be verified, it should not be used for anything else.
+///
+/// # Arguments
+/// * `token` - A slice of strings representing the token parts (header, payload, signature)
+/// * `public_key` - A string representing the RSA public key in PEM format
+/// * `algorithm` - The algorithm from the token header, either `RS256` or `RS512`
+///
+/// # Returns
+/// * `bool` - Returns true if the token signature is valid, false otherwise
+pub fn verify_legacy_pss(token: &[&str], public_key: &str, algorithm: &Algorithm) -> Result<bool, VerifyingTokenError> {
+    let key = DecodingKey::from_rsa_pem(public_key).map_err(key_error)?;
+    let (data, signature) = split_signature(token)?;
+    let rsa_pub = rsa_key(&key, algorithm)?;
 
-    // Match the algorithm to create the signature
-    // and verify the token
-    // must be done in this match statement as the keys are different types depending on the algorithm
     match algorithm {
-        Algorithm::RS256 => {
-            let verifying_key = VerifyingKey::<Sha256>::new(rsa_pub);
-            let signature = Signature::try_from(decoded_signature).expect("Failed to parse signature from bytes");
-            return Ok(verifying_key.verify(data.as_bytes(), &signature).is_ok())
-        }
-        Algorithm::RS512 => {
-            let verifying_key = VerifyingKey::<Sha512>::new(rsa_pub);
-            let signature = Signature::try_from(decoded_signature).expect("Failed to parse signature from bytes");
-            return Ok(verifying_key.verify(data.as_bytes(), &signature).is_ok())
-        }
+        Algorithm::RS256 => Ok(verify_pss::<Sha256>(rsa_pub, data.as_bytes(), &signature)),
+        Algorithm::RS512 => Ok(verify_pss::<Sha512>(rsa_pub, data.as_bytes(), &signature)),
+        _ => Err(VerifyingTokenError::Other(format!("{} was never issued with PSS by this crate", algorithm))),
     }
-}
\ No newline at end of file
+}
+
+/// Returns the RSA public key, or an error if the key is of another type
+fn rsa_key<'a>(key: &'a DecodingKey, algorithm: &Algorithm) -> Result<&'a RsaPublicKey, VerifyingTokenError> {
+    match key.kind.as_ref() {
+        DecodingKeyKind::Rsa(rsa_pub) => Ok(rsa_pub),
+        _ => Err(VerifyingTokenError::IncompatibleKey(format!("{} requires an RSA key", algorithm))),
+    }
+}
+
+/// Verifies an RSASSA-PKCS1-v1_5 signature using the digest `D`
+fn verify_pkcs1v15<D>(rsa_pub: &RsaPublicKey, data: &[u8], signature: &[u8]) -> bool
+where
+    D: Digest + rsa::pkcs8::AssociatedOid,
+{
+    rsa_pub.verify(Pkcs1v15Sign::new::<D>(), &D::digest(data), signature).is_ok()
+}
+
+/// Verifies an RSASSA-PSS signature using the digest `D`, with the salt length equal to the digest size
+fn verify_pss<D>(rsa_pub: &RsaPublicKey, data: &[u8], signature: &[u8]) -> bool
+where
+    D: Digest + rsa::sha2::digest::DynDigest + Send + Sync + 'static,
+{
+    rsa_pub.verify(Pss::new::<D>(), &D::digest(data), signature).is_ok()
+}
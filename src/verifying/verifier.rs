@@ -0,0 +1,60 @@
+use std::future::Future;
+
+use crate::keys::decoding_key::DecodingKey;
+use crate::model::header::Algorithm;
+
+use super::{ecdsa, eddsa, hmac, rsa, VerifyingTokenError};
+
+/// Verifies the signature of a token over its signing input, the base64 encoded header and payload joined with a dot
+///
+/// Implement this to verify with a key held by another backend, e.g. a hardware security module or a cloud key service,
+/// and pass the verifier to `verify_with_key` in place of a `DecodingKey`.
+/// `DecodingKey` implements it for the built-in RSA, EC, EdDSA and HMAC keys.
+pub trait Verifier {
+    /// Verifies the signature of the signing input with the algorithm of the token
+    ///
+    /// # Arguments
+    /// * `signing_input` - The base64 encoded header and payload joined with a dot
+    /// * `signature` - The decoded signature of the token
+    /// * `algorithm` - The algorithm in the token header, which the verifier should reject if its key cannot be used with it
+    ///
+    /// # Returns
+    /// * `Result<bool, VerifyingTokenError>` - Whether the signature is valid, or an error if it cannot be checked
+    fn verify(&self, signing_input: &[u8], signature: &[u8], algorithm: &Algorithm) -> Result<bool, VerifyingTokenError>;
+}
+
+/// The asynchronous form of `Verifier`, for backends that verify over the network, used by `verify_async`
+///
+/// Every `Verifier` that is `Sync` is also an `AsyncVerifier`, so the built-in keys can be used with `verify_async` as well.
+pub trait AsyncVerifier {
+    /// Verifies the signature of the signing input with the algorithm of the token
+    ///
+    /// # Arguments
+    /// * `signing_input` - The base64 encoded header and payload joined with a dot
+    /// * `signature` - The decoded signature of the token
+    /// * `algorithm` - The algorithm in the token header, which the verifier should reject if its key cannot be used with it
+    ///
+    /// # Returns
+    /// * `Result<bool, VerifyingTokenError>` - Whether the signature is valid, or an error if it cannot be checked
+    fn verify(&self, signing_input: &[u8], signature: &[u8], algorithm: &Algorithm) -> impl Future<Output = Result<bool, VerifyingTokenError>> + Send;
+}
+
+impl<V: Verifier + Sync + ?Sized> AsyncVerifier for V {
+    fn verify(&self, signing_input: &[u8], signature: &[u8], algorithm: &Algorithm) -> impl Future<Output = Result<bool, VerifyingTokenError>> + Send {
+        std::future::ready(Verifier::verify(self, signing_input, signature, algorithm))
+    }
+}
+
+impl Verifier for DecodingKey {
+    /// Verifies with the built-in key, which must be of the type the algorithm requires
+    fn verify(&self, signing_input: &[u8], signature: &[u8], algorithm: &Algorithm) -> Result<bool, VerifyingTokenError> {
+        match algorithm {
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512 => rsa::verify_signature(signing_input, signature, self, algorithm),
+            Algorithm::ES256 | Algorithm::ES384 | Algorithm::ES512 | Algorithm::ES256K => ecdsa::verify_signature(signing_input, signature, self, algorithm),
+            Algorithm::EdDSA => eddsa::verify_signature(signing_input, signature, self, algorithm),
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => hmac::verify_signature(signing_input, signature, self, algorithm),
+            // Unsecured tokens are never verified, even with the `dangerous-unsecured` feature
+            Algorithm::None => Err(VerifyingTokenError::UnsecuredToken),
+        }
+    }
+}